@@ -0,0 +1,139 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use solana_payments::{Number, PublicKey, SolanaPayUrl, Utils};
+
+// Counts the heap allocations so the benches can report the allocations
+// of parsing with and without the `smallvec` feature
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+const REFERENCES_STORAGE: &str = if cfg!(feature = "smallvec") {
+    "smallvec"
+} else {
+    "vec"
+};
+
+const RECIPIENT: &str = "mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN";
+const URL: &str = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=0.01&spl-token=EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v&reference=7owWEdgJRWpKsiDFNU4qT2kgMe2kitPXem5Yy8VdNatx&label=Michael&message=Thanks%20for%20all%20the%20fish&memo=OrderId12345";
+
+fn parse(c: &mut Criterion) {
+    let lookup_fn = |_| async { 6 };
+
+    c.bench_function("parse", |b| {
+        b.iter(|| {
+            smol::block_on(async {
+                SolanaPayUrl::new()
+                    .parse(black_box(URL), lookup_fn)
+                    .await
+                    .unwrap()
+            })
+        })
+    });
+}
+
+// Run with and without `--features smallvec` to compare the time and the allocations
+// of parsing 1 and 4 references, the benches are named after the storage of the references
+fn parse_references_storage(c: &mut Criterion) {
+    let single = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1&reference=7owWEdgJRWpKsiDFNU4qT2kgMe2kitPXem5Yy8VdNatx";
+    let four = (0..4u8).fold(
+        String::from("solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1"),
+        |url, index| url + "&reference=" + &Utils::to_base58([index; 32]),
+    );
+
+    let mut group = c.benchmark_group(format!("parse references ({REFERENCES_STORAGE})"));
+
+    for (name, url) in [("single", single), ("four", four.as_str())] {
+        let parse = || {
+            smol::block_on(async {
+                SolanaPayUrl::new()
+                    .parse(black_box(url), Utils::native_sol)
+                    .await
+                    .unwrap()
+            })
+        };
+
+        // The first call also allocates the thread local state of the executor
+        drop(parse());
+        let before = ALLOCATIONS.load(Ordering::Relaxed);
+        drop(parse());
+        let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+        println!("parse references ({REFERENCES_STORAGE})/{name}: {allocations} allocations");
+
+        group.bench_function(name, |b| b.iter(parse));
+    }
+
+    group.finish();
+}
+
+// Guards against parsing becoming quadratic in the number of references
+fn parse_max_references(c: &mut Criterion) {
+    let url = (0..solana_payments::MAX_ACCOUNTS_PER_TX).fold(
+        String::from("solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN"),
+        |url, index| url + "&reference=" + &Utils::to_base58([index as u8; 32]),
+    );
+
+    c.bench_function("parse max references", |b| {
+        b.iter(|| {
+            smol::block_on(async {
+                SolanaPayUrl::new()
+                    .parse(black_box(&url), Utils::native_sol)
+                    .await
+                    .unwrap()
+            })
+        })
+    });
+}
+
+fn to_url(c: &mut Criterion) {
+    let url = smol::block_on(async { SolanaPayUrl::new().parse(URL, Utils::native_sol).await });
+    let url = url.unwrap();
+
+    c.bench_function("to_url", |b| b.iter(|| black_box(&url).to_url()));
+}
+
+fn public_key_from_base58(c: &mut Criterion) {
+    c.bench_function("PublicKey::from_base58", |b| {
+        b.iter(|| PublicKey::from_base58(black_box(RECIPIENT)).unwrap())
+    });
+}
+
+fn number_parse(c: &mut Criterion) {
+    c.bench_function("Number::parse", |b| {
+        b.iter(|| Number::new(black_box("1000.000001")).parse().unwrap())
+    });
+}
+
+criterion_group!(
+    benches,
+    parse,
+    parse_references_storage,
+    parse_max_references,
+    to_url,
+    public_key_from_base58,
+    number_parse
+);
+criterion_main!(benches);
@@ -15,7 +15,7 @@ async fn main() {
     };
 
     let url  = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=0.01&spl-token=EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
-    let url_decoded = SolanaPayUrl::new().parse(&url, lookup_fn).await.unwrap();
+    let url_decoded = SolanaPayUrl::new().parse(url, lookup_fn).await.unwrap();
 
     dbg!(url_decoded);
 }
@@ -0,0 +1,142 @@
+use crate::{SolanaPayError, SolanaPayResult, SolanaPayUrl};
+
+/// Builds a [SolanaPayUrl] without handling an error after every field, created by
+/// [SolanaPayUrl::builder]. Each method applies the matching fallible `add_*` method
+/// of [SolanaPayUrl] and stores the first error which is returned by [SolanaPayUrlBuilder::build].
+/// Once an error is stored the remaining fields are ignored.
+/// ```rust
+/// use solana_payments::{SolanaPayError, SolanaPayUrl};
+///
+/// let url = SolanaPayUrl::builder()
+///     .recipient("mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN")
+///     .amount("1")
+///     .label("Michael")
+///     .build()
+///     .unwrap();
+/// assert_eq!(url.to_url(), "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1&label=Michael");
+///
+/// let outcome = SolanaPayUrl::builder()
+///     .recipient("mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN")
+///     .amount(".5")
+///     .label("Michael")
+///     .build();
+/// assert_eq!(outcome, Err(SolanaPayError::MissingLeadingZero));
+/// ```
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct SolanaPayUrlBuilder<'a> {
+    url: SolanaPayUrl<'a>,
+    error: Option<SolanaPayError>,
+}
+
+impl<'a> SolanaPayUrlBuilder<'a> {
+    /// Set the recipient, see [SolanaPayUrl::add_recipient]
+    pub fn recipient(self, base58_public_key: &str) -> Self {
+        self.apply(|url| url.add_recipient(base58_public_key))
+    }
+
+    /// Set the amount, see [SolanaPayUrl::add_amount]
+    pub fn amount(self, amount: &'a str) -> Self {
+        self.apply(|url| url.add_amount(amount))
+    }
+
+    /// Set the spl-token, see [SolanaPayUrl::add_spl_token]
+    pub fn spl_token(self, spl_token: &str) -> Self {
+        self.apply(|url| url.add_spl_token(spl_token))
+    }
+
+    /// Add a reference, see [SolanaPayUrl::add_reference]
+    pub fn reference(self, base58_reference: &str) -> Self {
+        self.apply(|url| url.add_reference(base58_reference))
+    }
+
+    /// Set the label, see [SolanaPayUrl::add_label]
+    pub fn label(self, label: &'a str) -> Self {
+        self.apply(|url| url.add_label(label))
+    }
+
+    /// Set the message, see [SolanaPayUrl::add_message]
+    pub fn message(self, message: &'a str) -> Self {
+        self.apply(|url| url.add_message(message))
+    }
+
+    /// Set the memo, see [SolanaPayUrl::add_spl_memo]
+    pub fn spl_memo(self, spl_memo: &'a str) -> Self {
+        self.apply(|url| url.add_spl_memo(spl_memo))
+    }
+
+    /// Returns the [SolanaPayUrl] or the first error encountered while building it
+    pub fn build(self) -> SolanaPayResult<SolanaPayUrl<'a>> {
+        match self.error {
+            Some(error) => Err(error),
+            None => Ok(self.url),
+        }
+    }
+
+    fn apply(
+        mut self,
+        add: impl FnOnce(SolanaPayUrl<'a>) -> SolanaPayResult<SolanaPayUrl<'a>>,
+    ) -> Self {
+        if self.error.is_some() {
+            return self;
+        }
+
+        match add(core::mem::take(&mut self.url)) {
+            Ok(url) => self.url = url,
+            Err(error) => self.error = Some(error),
+        }
+
+        self
+    }
+}
+
+impl<'a> SolanaPayUrl<'a> {
+    /// Create a [SolanaPayUrlBuilder] which reports the first error at
+    /// [SolanaPayUrlBuilder::build] instead of after every field
+    pub fn builder() -> SolanaPayUrlBuilder<'a> {
+        SolanaPayUrlBuilder::default()
+    }
+}
+
+#[cfg(test)]
+mod test_builder {
+    use crate::{SolanaPayError, SolanaPayUrl};
+
+    #[test]
+    fn matches_fallible_api() {
+        let built = SolanaPayUrl::builder()
+            .recipient("mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN")
+            .amount("0.5")
+            .spl_token("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v")
+            .reference("7owWEdgJRWpKsiDFNU4qT2kgMe2kitPXem5Yy8VdNatx")
+            .label("Michael")
+            .message("Thanks for all the fish")
+            .spl_memo("OrderId12345")
+            .build();
+
+        let fallible = SolanaPayUrl::new()
+            .add_recipient("mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN")
+            .and_then(|url| url.add_amount("0.5"))
+            .and_then(|url| url.add_spl_token("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v"))
+            .and_then(|url| url.add_reference("7owWEdgJRWpKsiDFNU4qT2kgMe2kitPXem5Yy8VdNatx"))
+            .and_then(|url| url.add_label("Michael"))
+            .and_then(|url| url.add_message("Thanks for all the fish"))
+            .and_then(|url| url.add_spl_memo("OrderId12345"));
+
+        assert_eq!(built, fallible);
+        assert!(built.is_ok());
+    }
+
+    #[test]
+    fn mid_chain_error_surfaces_at_build() {
+        let outcome = SolanaPayUrl::builder()
+            .recipient("mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN")
+            .amount("1")
+            .amount("2")
+            .label("Michael")
+            .reference("0OIl")
+            .build();
+
+        // Only the first error is returned
+        assert_eq!(outcome, Err(SolanaPayError::AmountAlreadyExists));
+    }
+}
@@ -1,5 +1,7 @@
 use thiserror::Error;
 
+use crate::QueryParam;
+
 /// The result type with [SolanaPayError] as the error type
 pub type SolanaPayResult<T> = Result<T, SolanaPayError>;
 
@@ -7,58 +9,338 @@ pub type SolanaPayResult<T> = Result<T, SolanaPayError>;
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Error)]
 pub enum SolanaPayError {
     /// The `Amount` from the Solana Pay URL is invalid
-    #[error("The `Amount` from the Solana Pay URL is invalid")]
+    #[error("The `Amount` from the Solana Pay URL is invalid, use a non-negative integer or decimal number like `1` or `0.01`")]
     InvalidNumber,
+    /// The `Amount` is less than 1 and has no leading `0` before the `.` like `.5`
+    #[error("The `Amount` is missing a leading zero, add a `0` before the `.` like `0.5`")]
+    MissingLeadingZero,
+    /// The `Amount` has more than one decimal point like `1.1.1`
+    #[error("The `Amount` has more than one decimal point, use a single `.` like `1.1`")]
+    MultipleDecimalPoints,
+    /// The `Amount` is a percentage like `15%`
+    #[error(
+        "The `Amount` is a percentage, convert the percentage to the amount to transfer like `1.5`"
+    )]
+    PercentNotAllowed,
+    /// The `Amount` string is longer than [crate::MAX_AMOUNT_STR_LEN] characters
+    #[error("The `Amount` string is longer than the maximum of {} characters, check that the amount is not malformed", crate::MAX_AMOUNT_STR_LEN)]
+    AmountStringTooLong,
+    /// The `Amount` is too large to be represented
+    #[error("The `Amount` is too large to be represented, use a smaller amount")]
+    AmountTooLarge,
+    /// The `Amount` is outside the bounds required by the merchant
+    #[error("The `Amount` is outside the bounds required by the merchant, check the amount used to generate the URL")]
+    AmountOutOfBounds,
+    /// The minimum amount bound is larger than the maximum amount bound
+    #[error("The minimum amount bound is larger than the maximum amount bound, swap the bounds")]
+    InvalidAmountBounds,
+    /// The result of an operation on an `Amount` is negative
+    #[error("The result of the operation on the `Amount` is negative, Solana Pay amounts must not be negative")]
+    NegativeAmountNotAllowed,
+    /// The amount has a fractional part but only whole amounts are allowed
+    #[error("The amount has a fractional part but only whole amounts are allowed, remove the fractional part of the `amount`")]
+    FractionalAmountNotAllowed,
+    /// The amount has the `lamports` suffix but the URL is for an SPL Token transfer
+    #[error("The `lamports` amount suffix is only valid for native SOL, give the `amount` in the units of the spl-token instead")]
+    LamportsAmountWithSplToken,
+    /// The amount of an SPL Token transfer is not the canonical `uiAmountString`
+    #[error("The amount of an SPL Token transfer is not the canonical `uiAmountString`, remove the trailing zeroes of the `amount` like `1.5` instead of `1.50`")]
+    NonCanonicalUiAmount,
     /// The Base58 str provided is invalid
-    #[error("he Base58 str provided is invalid")]
+    #[error(
+        "The Base58 str provided is invalid, check that it is a Base58 encoded 32 byte public key"
+    )]
     InvalidBase58Str,
+    /// A character that is not in the Base58 alphabet was found in a public key
+    #[error("The character `{c}` at index {index} is not in the Base58 alphabet, Base58 never contains `0`, `O`, `I` or `l`")]
+    InvalidBase58Character {
+        /// The character that is not in the Base58 alphabet
+        c: char,
+        /// The byte index of the character in the public key
+        index: usize,
+    },
+    /// The public key looks like it is hex encoded instead of Base58 encoded
+    #[error("The public key looks like it is hex encoded instead of Base58 encoded, use the Base58 encoding of the public key")]
+    LooksLikeHexNotBase58,
+    /// The public key decodes to 64 bytes which is the length of a secret key
+    #[error("The public key decodes to 64 bytes like a secret key, never share the secret key and use the Base58 encoded public key of the wallet instead")]
+    LooksLikeSecretKeyNotPublicKey,
+    /// The spl-token or a reference is percent-encoded
+    #[error("The spl-token or a reference is percent-encoded, use the plain Base58 value since it never needs encoding")]
+    UnexpectedEncodingInKeyField,
     /// Invalid Ed25519 public key
-    #[error("Invalid Ed25519 public key")]
+    #[error("Invalid Ed25519 public key, check that the public key is a Base58 encoded 32 byte Ed25519 public key")]
     InvalidEd25519PublicKey,
     /// The recipient is expected to be on curve
     /// to prevent sending to a PDA without user's
-    /// knowledge. Use `new_all_accounts()` if you
-    /// want to support all types of recipients
-    #[error("The recipient is expected to be on curve to prevent sending to a PDA without user's knowledge. Use `new_any_public_key()` if you want to support all types of recipients")]
+    /// knowledge
+    #[error("The recipient is expected to be on curve to prevent sending to a PDA without user's knowledge, use the public key of a wallet account as the recipient")]
     ExpectedRecipientPublicKeyOnCurve,
     /// The number of decimals in a number
     /// exceeds those of Native SOL (9 decimals)
-    #[error("The number of decimals in a number exceeds those of Native SOL (9 decimals)")]
+    #[error("The number of decimals in a number exceeds those of Native SOL (9 decimals), round the amount to at most 9 decimal places")]
     NumberOfDecimalsExceeds9,
     /// The number of decimals in a number
     /// exceeds those configured by the mint
-    #[error("The number of decimals in a number exceeds those configured by the mint")]
+    #[error("The number of decimals in a number exceeds those configured by the mint, round the amount to the decimals of the mint")]
     NumberOfDecimalsExceedsMintConfiguration,
     /// The capacity left in the references container
     /// is smaller than the references provided
     /// as arguments
-    #[error("The capacity left in the references container is smaller than the references provided as arguments")]
+    #[error("The capacity left in the references container is smaller than the references provided as arguments, remove some of the `reference` parameters")]
     TooManyReferences,
     /// The characters contain Invalid UTF8
-    #[error("The characters contain Invalid UTF8")]
+    #[error(
+        "The characters contain Invalid UTF8, percent-encode the label, message and memo as UTF-8"
+    )]
     InvalidUrlEncodedString,
+    /// The scheme, recipient, query parameter names, amount, spl-token and references
+    /// of a Solana Pay URL are expected to only contain ASCII characters
+    #[error("The scheme, recipient, query parameter names, amount, spl-token and references of a Solana Pay URL are expected to only contain ASCII characters, remove any non-ASCII characters from them")]
+    ExpectedAsciiCharacters,
     /// Invalid Parameter of a Solana Pay URL
-    #[error("Invalid Parameter of a Solana Pay URL")]
+    #[error("Invalid Parameter of a Solana Pay URL, only `amount`, `spl-token`, `reference`, `label`, `message` and `memo` are supported")]
     InvalidQueryParam,
+    /// The URL does not start with the `solana:` scheme
+    #[error(
+        "The URL does not start with the `solana:` scheme, prefix the recipient with `solana:`"
+    )]
+    InvalidSolanaPayScheme,
+    /// The URL contains more than one `?`
+    #[error("The URL contains more than one `?`, separate the query parameters with `&`")]
+    TooManySolanaPayUrlParts,
+    /// A query parameter is not a `key=value` pair
+    #[error("The query parameter `{param}` is malformed, use a `key=value` pair like `amount=1`")]
+    MalformedQuery {
+        /// The malformed query parameter
+        param: String,
+    },
     /// Found duplicate amount in a Solana Pay URL
-    #[error("Found duplicate amount in a Solana Pay URL")]
+    #[error("Found duplicate amount in a Solana Pay URL, remove the duplicate `amount` parameter")]
     AmountAlreadyExists,
     /// Found duplicate spl-token in a Solana Pay URL
-    #[error("Found duplicate spl-token in a Solana Pay URL")]
+    #[error(
+        "Found duplicate spl-token in a Solana Pay URL, remove the duplicate `spl-token` parameter"
+    )]
     SplTokenAlreadyExists,
     /// Found duplicate label in a Solana Pay URL
-    #[error("Found duplicate label in a Solana Pay URL")]
+    #[error("Found duplicate label in a Solana Pay URL, remove the duplicate `label` parameter")]
     LabelAlreadyExists,
     /// Found duplicate message in a Solana Pay URL
-    #[error("Found duplicate message in a Solana Pay URL")]
+    #[error(
+        "Found duplicate message in a Solana Pay URL, remove the duplicate `message` parameter"
+    )]
     MessageAlreadyExists,
     /// Found duplicate memo in a Solana Pay URL
-    #[error("Found duplicate memo in a Solana Pay URL")]
+    #[error("Found duplicate memo in a Solana Pay URL, remove the duplicate `memo` parameter")]
     MemoAlreadyExists,
+    /// The decoded memo is longer than [crate::MAX_MEMO_BYTES] UTF-8 bytes
+    #[error("The decoded memo is longer than the maximum of {} UTF-8 bytes, shorten the memo keeping in mind that emoji take up 4 bytes each", crate::MAX_MEMO_BYTES)]
+    MemoTooLong,
+    /// The query parameter is not allowed by the parse options
+    #[error("The query parameter `{0}` is not allowed, remove the `{0}` parameter")]
+    ParameterNotAllowed(QueryParam),
+    /// The query parameter containing the Solana Pay URL was not found in the universal link
+    #[error("The query parameter containing the Solana Pay URL was not found in the universal link, check the name of the query parameter")]
+    MissingDeepLinkParam,
     /// Expected an async function to lookup the number of decimals in a mint
-    #[error("Expected an async function to lookup the number of decimals in a mint")]
+    #[error("Expected an async function to lookup the number of decimals in a mint, pass a function that fetches the decimals of the spl-token mint")]
     ExpectedALookupFunction,
+    /// The recipient is the same as the spl-token, which is most likely
+    /// the mint address pasted as the recipient by mistake
+    #[error("The recipient is the same as the spl-token, use the public key of the wallet receiving the tokens as the recipient instead of the mint")]
+    RecipientEqualsSplToken,
+    /// The recipient is one of the addresses forbidden by the parse options
+    #[error("The recipient is a forbidden address like the incinerator, check that the recipient is the intended account")]
+    ForbiddenRecipient,
+    /// The recipient is the all-zero public key of the System Program
+    #[error("The recipient is the all-zero public key of the System Program which would burn the funds, use the public key of the wallet receiving the funds")]
+    SystemProgramRecipientNotAllowed,
+    /// A reference is the same account as the recipient or the spl-token
+    #[error("A reference is the same account as the recipient or the spl-token, use a unique reference that does not collide with the accounts of the transfer")]
+    ReferenceCollidesWithAccount,
+    /// The same reference is provided more than once
+    #[error(
+        "The same reference is provided more than once, remove the duplicate `reference` parameter"
+    )]
+    DuplicateReference,
+    /// A reference has an empty value
+    #[error("A reference has an empty value, remove the empty `reference=` parameter or provide a Base58 encoded 32 byte array")]
+    EmptyReference,
+    /// The owner of the spl-token mint is not a known token program
+    #[error("The owner of the spl-token mint is not a known token program, check that the spl-token is the mint of a Token or Token-2022 token")]
+    UnknownTokenProgram,
+    /// The OS random number generator is unavailable
+    #[error("The OS random number generator is unavailable, retry later or derive the reference with `Reference::from_order_id`")]
+    EntropyUnavailable,
     /// The slice provided is not 32 bytes
-    #[error("The slice provided is not 32 bytes")]
+    #[error(
+        "The slice provided is not 32 bytes, pass exactly 32 bytes like the bytes of a public key"
+    )]
     Expected32ByteSlice,
 }
+
+#[cfg(test)]
+mod test_errors {
+    use crate::{QueryParam, SolanaPayError};
+
+    #[test]
+    fn messages_include_remediation() {
+        assert_eq!(
+            SolanaPayError::AmountAlreadyExists.to_string(),
+            "Found duplicate amount in a Solana Pay URL, remove the duplicate `amount` parameter"
+        );
+        assert_eq!(
+            SolanaPayError::MemoAlreadyExists.to_string(),
+            "Found duplicate memo in a Solana Pay URL, remove the duplicate `memo` parameter"
+        );
+        assert_eq!(
+            SolanaPayError::InvalidBase58Str.to_string(),
+            "The Base58 str provided is invalid, check that it is a Base58 encoded 32 byte public key"
+        );
+        assert_eq!(
+            SolanaPayError::ParameterNotAllowed(QueryParam::SplToken).to_string(),
+            "The query parameter `spl-token` is not allowed, remove the `spl-token` parameter"
+        );
+        assert_eq!(
+            SolanaPayError::AmountStringTooLong.to_string(),
+            "The `Amount` string is longer than the maximum of 40 characters, check that the amount is not malformed"
+        );
+        assert_eq!(
+            SolanaPayError::MemoTooLong.to_string(),
+            "The decoded memo is longer than the maximum of 566 UTF-8 bytes, shorten the memo keeping in mind that emoji take up 4 bytes each"
+        );
+    }
+
+    // One value of every variant. A new variant fails to compile until it gets an arm
+    // in the exhaustive match of `every_variant_listed`, list it here along with the arm
+    fn all_variants() -> Vec<SolanaPayError> {
+        vec![
+            SolanaPayError::InvalidNumber,
+            SolanaPayError::MissingLeadingZero,
+            SolanaPayError::MultipleDecimalPoints,
+            SolanaPayError::PercentNotAllowed,
+            SolanaPayError::AmountStringTooLong,
+            SolanaPayError::AmountTooLarge,
+            SolanaPayError::AmountOutOfBounds,
+            SolanaPayError::InvalidAmountBounds,
+            SolanaPayError::NegativeAmountNotAllowed,
+            SolanaPayError::FractionalAmountNotAllowed,
+            SolanaPayError::LamportsAmountWithSplToken,
+            SolanaPayError::NonCanonicalUiAmount,
+            SolanaPayError::InvalidBase58Str,
+            SolanaPayError::InvalidBase58Character { c: '0', index: 0 },
+            SolanaPayError::LooksLikeHexNotBase58,
+            SolanaPayError::LooksLikeSecretKeyNotPublicKey,
+            SolanaPayError::UnexpectedEncodingInKeyField,
+            SolanaPayError::InvalidEd25519PublicKey,
+            SolanaPayError::ExpectedRecipientPublicKeyOnCurve,
+            SolanaPayError::NumberOfDecimalsExceeds9,
+            SolanaPayError::NumberOfDecimalsExceedsMintConfiguration,
+            SolanaPayError::TooManyReferences,
+            SolanaPayError::InvalidUrlEncodedString,
+            SolanaPayError::ExpectedAsciiCharacters,
+            SolanaPayError::InvalidQueryParam,
+            SolanaPayError::InvalidSolanaPayScheme,
+            SolanaPayError::TooManySolanaPayUrlParts,
+            SolanaPayError::MalformedQuery {
+                param: "amount".to_string(),
+            },
+            SolanaPayError::AmountAlreadyExists,
+            SolanaPayError::SplTokenAlreadyExists,
+            SolanaPayError::LabelAlreadyExists,
+            SolanaPayError::MessageAlreadyExists,
+            SolanaPayError::MemoAlreadyExists,
+            SolanaPayError::MemoTooLong,
+            SolanaPayError::ParameterNotAllowed(QueryParam::Amount),
+            SolanaPayError::MissingDeepLinkParam,
+            SolanaPayError::ExpectedALookupFunction,
+            SolanaPayError::RecipientEqualsSplToken,
+            SolanaPayError::ForbiddenRecipient,
+            SolanaPayError::SystemProgramRecipientNotAllowed,
+            SolanaPayError::ReferenceCollidesWithAccount,
+            SolanaPayError::DuplicateReference,
+            SolanaPayError::EmptyReference,
+            SolanaPayError::UnknownTokenProgram,
+            SolanaPayError::EntropyUnavailable,
+            SolanaPayError::Expected32ByteSlice,
+        ]
+    }
+
+    #[test]
+    fn every_variant_listed() {
+        let listed = all_variants()
+            .iter()
+            .map(|error| match error {
+                SolanaPayError::InvalidNumber => "InvalidNumber",
+                SolanaPayError::MissingLeadingZero => "MissingLeadingZero",
+                SolanaPayError::MultipleDecimalPoints => "MultipleDecimalPoints",
+                SolanaPayError::PercentNotAllowed => "PercentNotAllowed",
+                SolanaPayError::AmountStringTooLong => "AmountStringTooLong",
+                SolanaPayError::AmountTooLarge => "AmountTooLarge",
+                SolanaPayError::AmountOutOfBounds => "AmountOutOfBounds",
+                SolanaPayError::InvalidAmountBounds => "InvalidAmountBounds",
+                SolanaPayError::NegativeAmountNotAllowed => "NegativeAmountNotAllowed",
+                SolanaPayError::FractionalAmountNotAllowed => "FractionalAmountNotAllowed",
+                SolanaPayError::LamportsAmountWithSplToken => "LamportsAmountWithSplToken",
+                SolanaPayError::NonCanonicalUiAmount => "NonCanonicalUiAmount",
+                SolanaPayError::InvalidBase58Str => "InvalidBase58Str",
+                SolanaPayError::InvalidBase58Character { .. } => "InvalidBase58Character",
+                SolanaPayError::LooksLikeHexNotBase58 => "LooksLikeHexNotBase58",
+                SolanaPayError::LooksLikeSecretKeyNotPublicKey => "LooksLikeSecretKeyNotPublicKey",
+                SolanaPayError::UnexpectedEncodingInKeyField => "UnexpectedEncodingInKeyField",
+                SolanaPayError::InvalidEd25519PublicKey => "InvalidEd25519PublicKey",
+                SolanaPayError::ExpectedRecipientPublicKeyOnCurve => {
+                    "ExpectedRecipientPublicKeyOnCurve"
+                }
+                SolanaPayError::NumberOfDecimalsExceeds9 => "NumberOfDecimalsExceeds9",
+                SolanaPayError::NumberOfDecimalsExceedsMintConfiguration => {
+                    "NumberOfDecimalsExceedsMintConfiguration"
+                }
+                SolanaPayError::TooManyReferences => "TooManyReferences",
+                SolanaPayError::InvalidUrlEncodedString => "InvalidUrlEncodedString",
+                SolanaPayError::ExpectedAsciiCharacters => "ExpectedAsciiCharacters",
+                SolanaPayError::InvalidQueryParam => "InvalidQueryParam",
+                SolanaPayError::InvalidSolanaPayScheme => "InvalidSolanaPayScheme",
+                SolanaPayError::TooManySolanaPayUrlParts => "TooManySolanaPayUrlParts",
+                SolanaPayError::MalformedQuery { .. } => "MalformedQuery",
+                SolanaPayError::AmountAlreadyExists => "AmountAlreadyExists",
+                SolanaPayError::SplTokenAlreadyExists => "SplTokenAlreadyExists",
+                SolanaPayError::LabelAlreadyExists => "LabelAlreadyExists",
+                SolanaPayError::MessageAlreadyExists => "MessageAlreadyExists",
+                SolanaPayError::MemoAlreadyExists => "MemoAlreadyExists",
+                SolanaPayError::MemoTooLong => "MemoTooLong",
+                SolanaPayError::ParameterNotAllowed(_) => "ParameterNotAllowed",
+                SolanaPayError::MissingDeepLinkParam => "MissingDeepLinkParam",
+                SolanaPayError::ExpectedALookupFunction => "ExpectedALookupFunction",
+                SolanaPayError::RecipientEqualsSplToken => "RecipientEqualsSplToken",
+                SolanaPayError::ForbiddenRecipient => "ForbiddenRecipient",
+                SolanaPayError::SystemProgramRecipientNotAllowed => {
+                    "SystemProgramRecipientNotAllowed"
+                }
+                SolanaPayError::ReferenceCollidesWithAccount => "ReferenceCollidesWithAccount",
+                SolanaPayError::DuplicateReference => "DuplicateReference",
+                SolanaPayError::EmptyReference => "EmptyReference",
+                SolanaPayError::UnknownTokenProgram => "UnknownTokenProgram",
+                SolanaPayError::EntropyUnavailable => "EntropyUnavailable",
+                SolanaPayError::Expected32ByteSlice => "Expected32ByteSlice",
+            })
+            .collect::<std::collections::HashSet<&str>>();
+
+        // Each variant is listed exactly once
+        assert_eq!(listed.len(), all_variants().len());
+    }
+
+    #[test]
+    fn every_variant_has_remediation() {
+        for error in all_variants() {
+            let message = error.to_string();
+            let remediation = message.split_once(", ").map(|(_, hint)| hint);
+
+            assert!(
+                remediation.is_some_and(|hint| !hint.is_empty()),
+                "{error:?} has no remediation hint: {message}"
+            );
+        }
+    }
+}
@@ -23,5 +23,25 @@ pub use references::*;
 mod parser;
 pub use parser::*;
 
+mod builder;
+pub use builder::*;
+
+mod options;
+pub use options::*;
+
+mod spans;
+pub use spans::*;
+
+mod stats;
+pub use stats::*;
+
+mod warnings;
+pub use warnings::*;
+
+#[cfg(feature = "serde")]
+mod serde_support;
+
 mod types;
 pub use types::*;
+
+pub mod prelude;
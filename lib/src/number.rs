@@ -1,4 +1,7 @@
-use crate::{SolanaPayError, SolanaPayResult};
+use core::fmt;
+use std::borrow::Cow;
+
+use crate::{SolanaPayError, SolanaPayResult, MAX_AMOUNT_INTEGRAL_DIGITS, MAX_AMOUNT_STR_LEN};
 
 /// Parse a number that can a fractional part.
 #[derive(Debug, PartialEq, Default, Eq, PartialOrd, Ord, Hash, Clone)]
@@ -12,7 +15,7 @@ pub struct Number<'a> {
     /// Number of significant digits  
     pub significant_digits_count: usize,
     /// The string representation of the number
-    pub as_string: &'a str,
+    pub as_string: Cow<'a, str>,
     /// The total count of the significant fractional part and leading zeroes
     pub total_fractional_count: usize,
 }
@@ -21,28 +24,105 @@ impl<'a> Number<'a> {
     /// instantiate the struct with the [str] representation of the number
     pub fn new(str_number: &'a str) -> Self {
         Self {
-            as_string: str_number,
+            as_string: Cow::Borrowed(str_number),
+            ..Default::default()
+        }
+    }
+
+    /// Instantiate the struct with an owned [String] representation of the number
+    pub fn new_owned(str_number: String) -> Number<'static> {
+        Number {
+            as_string: Cow::Owned(str_number),
             ..Default::default()
         }
     }
 
+    /// Derive the canonical decimal representation of an `amount` in base units
+    /// (like lamports for native SOL) for a mint configured with `decimals`.
+    /// This is the `uiAmountString` defined by the SPL Token program where trailing
+    /// zeroes in the fractional part are removed, for example `1_500_000` base units
+    /// with `6` decimals is `1.5`.
+    pub fn from_base_units(amount: u64, decimals: u8) -> SolanaPayResult<Number<'static>> {
+        let amount = amount as u128;
+        let divisor = 10u128
+            .checked_pow(decimals as u32)
+            .ok_or(SolanaPayError::AmountTooLarge)?;
+
+        let integral = amount / divisor;
+        let fractional = amount % divisor;
+
+        let mut as_string = integral.to_string();
+
+        if fractional != 0 {
+            let fractional = format!("{:0width$}", fractional, width = decimals as usize);
+            as_string.push('.');
+            as_string.push_str(fractional.trim_end_matches('0'));
+        }
+
+        Number::new_owned(as_string).parse()
+    }
+
+    /// Derive the canonical decimal representation of an [f64] `value`, like an amount
+    /// from a JSON number, rounded to `decimals` decimal places with trailing zeroes removed.
+    ///
+    /// #### Precision loss
+    /// An [f64] cannot represent most decimal numbers exactly, for example `0.1 + 0.2`
+    /// is `0.30000000000000004`, so the value is rounded to `decimals` places and any digits
+    /// beyond them are lost. Large values also lose precision in their integral part.
+    /// Prefer parsing the amount from its string representation with [Number::new]
+    /// whenever it is available.
+    ///
+    /// NaN, infinite and negative values result in a [SolanaPayError::InvalidNumber] error
+    pub fn from_f64_rounded(value: f64, decimals: u8) -> SolanaPayResult<Number<'static>> {
+        if !value.is_finite() || value < 0.0 {
+            return Err(SolanaPayError::InvalidNumber);
+        }
+
+        // Adding zero turns `-0.0` into `0.0` so that it is not formatted with a sign
+        let mut as_string = format!("{:.*}", decimals as usize, value + 0.0);
+
+        if as_string.contains('.') {
+            let trimmed_len = as_string.trim_end_matches('0').trim_end_matches('.').len();
+            as_string.truncate(trimmed_len);
+        }
+
+        Number::new_owned(as_string).parse()
+    }
+
     /// Parse a number that may contain a fractional part
     pub fn parse(mut self) -> SolanaPayResult<Self> {
+        if self.as_string.len() > MAX_AMOUNT_STR_LEN {
+            return Err(SolanaPayError::AmountStringTooLong);
+        }
+
+        if self.as_string.ends_with('%') {
+            return Err(SolanaPayError::PercentNotAllowed);
+        }
+
         let convert_integral = |integral: &str| {
+            if integral.len() > MAX_AMOUNT_INTEGRAL_DIGITS {
+                return Err(SolanaPayError::AmountTooLarge);
+            }
+
             integral
                 .parse::<usize>()
-                .map_err(|_| SolanaPayError::InvalidNumber)
+                .map_err(|error| match error.kind() {
+                    core::num::IntErrorKind::PosOverflow => SolanaPayError::AmountTooLarge,
+                    _ => SolanaPayError::InvalidNumber,
+                })
         };
-        if !self.as_string.contains('.') {
-            self.integral = convert_integral(self.as_string)?;
+        let as_string = self.as_string.clone();
+
+        if !as_string.contains('.') {
+            self.integral = convert_integral(&as_string)?;
 
             return Ok(self);
         }
 
         let (str_integral, str_fractional) = {
-            let mut iter_str_number = self.as_string.split('.');
+            let mut iter_str_number = as_string.split('.');
             if iter_str_number.clone().nth(2).is_some() {
-                return Err(SolanaPayError::InvalidNumber);
+                return Err(SolanaPayError::MultipleDecimalPoints);
             }
 
             let str_integral = iter_str_number
@@ -56,6 +136,14 @@ impl<'a> Number<'a> {
             (str_integral, str_fractional)
         };
 
+        // The spec requires a leading `0` for numbers less than 1 like `0.5`
+        if str_integral.is_empty()
+            && !str_fractional.is_empty()
+            && str_fractional.bytes().all(|byte| byte.is_ascii_digit())
+        {
+            return Err(SolanaPayError::MissingLeadingZero);
+        }
+
         self.integral = convert_integral(str_integral)?;
 
         self.fractional_ops(str_fractional)?;
@@ -65,6 +153,144 @@ impl<'a> Number<'a> {
         Ok(self)
     }
 
+    /// The digits after the `.` exactly as they are written, including leading and trailing
+    /// zeroes, for example `50` for `1.50` and `001` for `0.001`. A number without a fractional
+    /// part returns an empty [str]
+    pub fn fractional_digits(&self) -> &str {
+        self.as_string
+            .split_once('.')
+            .map(|(_, fractional_digits)| fractional_digits)
+            .unwrap_or_default()
+    }
+
+    /// Returns `true` if the value of the parsed number has no fractional part.
+    /// This is decided by the value and not the literal, so `1.0` is an integer like `1`
+    /// while `0.5` is not. Use [Number::total_fractional_count] to check the literal
+    pub fn is_integer(&self) -> bool {
+        self.fractional == 0
+    }
+
+    /// Convert [Self] to a [Number] that owns the string representation
+    /// of the number so that it is no longer tied to the lifetime of the input
+    pub fn into_owned(self) -> Number<'static> {
+        Number {
+            integral: self.integral,
+            fractional: self.fractional,
+            leading_zeroes: self.leading_zeroes,
+            significant_digits_count: self.significant_digits_count,
+            as_string: Cow::Owned(self.as_string.into_owned()),
+            total_fractional_count: self.total_fractional_count,
+        }
+    }
+
+    /// Convert the number to a `(mantissa, scale)` pair where the value of the number
+    /// is `mantissa / 10^scale`. The scale is the number of digits after the decimal point
+    /// (including leading and trailing zeroes) so the conversion is lossless
+    /// and can be used for exact arithmetic in base units without using floats.
+    /// Returns [SolanaPayError::AmountTooLarge] if the mantissa cannot fit in a [u64].
+    pub fn to_mantissa_scale(&self) -> SolanaPayResult<(u64, u32)> {
+        let scale = self.total_fractional_count as u32;
+
+        let shifted_integral = if self.integral == 0 {
+            0
+        } else {
+            10u64
+                .checked_pow(scale)
+                .and_then(|multiplier| (self.integral as u64).checked_mul(multiplier))
+                .ok_or(SolanaPayError::AmountTooLarge)?
+        };
+
+        let mantissa = shifted_integral
+            .checked_add(self.fractional as u64)
+            .ok_or(SolanaPayError::AmountTooLarge)?;
+
+        Ok((mantissa, scale))
+    }
+
+    /// The canonical form of the number without trailing zeroes in the fractional part
+    /// or leading zeroes in the integral part, for example `0.010` is normalized to `0.01`
+    /// and `1.0` to `1`
+    pub fn normalized(&self) -> SolanaPayResult<Number<'static>> {
+        let (mantissa, scale) = self.to_mantissa_scale()?;
+
+        Number::from_base_units(mantissa, scale as u8)
+    }
+
+    /// Add two numbers exactly using their `(mantissa, scale)` representation
+    /// (see [Number::to_mantissa_scale]), for example to sum the line items of an invoice
+    /// in the same currency. The scales are aligned to the larger scale so no digits are lost.
+    /// Returns [SolanaPayError::AmountTooLarge] if the sum cannot fit in a [u64] mantissa
+    pub fn checked_add(&self, other: &Number) -> SolanaPayResult<Number<'static>> {
+        let (mantissa, other_mantissa, scale) = self.aligned_mantissas(other)?;
+
+        let sum = mantissa
+            .checked_add(other_mantissa)
+            .ok_or(SolanaPayError::AmountTooLarge)?;
+
+        Number::from_base_units(sum, scale as u8)
+    }
+
+    /// Subtract `other` from the number exactly, for example to compute change or a refund.
+    /// Solana Pay amounts are non-negative so a negative result is a
+    /// [SolanaPayError::NegativeAmountNotAllowed] error
+    pub fn checked_sub(&self, other: &Number) -> SolanaPayResult<Number<'static>> {
+        let (mantissa, other_mantissa, scale) = self.aligned_mantissas(other)?;
+
+        let difference = mantissa
+            .checked_sub(other_mantissa)
+            .ok_or(SolanaPayError::NegativeAmountNotAllowed)?;
+
+        Number::from_base_units(difference, scale as u8)
+    }
+
+    /// Format the number for display in a wallet UI with `group_sep` inserted between every
+    /// three digits of the integral part and `decimal_sep` as the decimal point,
+    /// for example `1000.5` is displayed as `1,000.5` or `1.000,5` in locales using a comma
+    /// as the decimal point. This is for display only, the grouped string is not a valid
+    /// Solana Pay amount so use [Number::as_string] when creating a URL
+    pub fn display_grouped(&self, group_sep: char, decimal_sep: char) -> String {
+        let (integral, fractional) = match self.as_string.split_once('.') {
+            Some((integral, fractional)) => (integral, Some(fractional)),
+            None => (self.as_string.as_ref(), None),
+        };
+
+        let mut grouped = String::with_capacity(self.as_string.len() + integral.len() / 3);
+
+        for (index, digit) in integral.chars().enumerate() {
+            if index != 0 && (integral.len() - index) % 3 == 0 {
+                grouped.push(group_sep);
+            }
+            grouped.push(digit);
+        }
+
+        if let Some(fractional) = fractional {
+            grouped.push(decimal_sep);
+            grouped.push_str(fractional);
+        }
+
+        grouped
+    }
+
+    fn aligned_mantissas(&self, other: &Number) -> SolanaPayResult<(u64, u64, u32)> {
+        let (mantissa, scale) = self.to_mantissa_scale()?;
+        let (other_mantissa, other_scale) = other.to_mantissa_scale()?;
+
+        let scale_up = |mantissa: u64, from_scale: u32, to_scale: u32| {
+            10u64
+                .checked_pow(to_scale - from_scale)
+                .and_then(|multiplier| mantissa.checked_mul(multiplier))
+                .ok_or(SolanaPayError::AmountTooLarge)
+        };
+
+        let aligned_scale = scale.max(other_scale);
+
+        Ok((
+            scale_up(mantissa, scale, aligned_scale)?,
+            scale_up(other_mantissa, other_scale, aligned_scale)?,
+            aligned_scale,
+        ))
+    }
+
     fn fractional_ops(&mut self, fractional_str: &str) -> SolanaPayResult<&mut Self> {
         let leading_zeroes_count = fractional_str
             .chars()
@@ -84,10 +310,62 @@ impl<'a> Number<'a> {
     }
 }
 
+impl fmt::Display for Number<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", &self.as_string)
+    }
+}
+
 #[cfg(test)]
 mod test_number_sanity {
     use crate::Number;
 
+    #[test]
+    fn fractional_digits() {
+        let number = Number::new("1.50").parse().unwrap();
+        assert_eq!(number.fractional_digits(), "50");
+
+        let number = Number::new("0.001").parse().unwrap();
+        assert_eq!(number.fractional_digits(), "001");
+
+        let number = Number::new("1").parse().unwrap();
+        assert_eq!(number.fractional_digits(), "");
+    }
+
+    #[test]
+    fn is_integer() {
+        let is_integer = |number| Number::new(number).parse().unwrap().is_integer();
+
+        assert!(is_integer("1"));
+        assert!(is_integer("0"));
+        assert!(is_integer("1.0"));
+        assert!(is_integer("1.000"));
+        assert!(!is_integer("0.5"));
+        assert!(!is_integer("1.000001"));
+    }
+
+    #[test]
+    fn display_grouped() {
+        let display = |number| {
+            Number::new(number)
+                .parse()
+                .unwrap()
+                .display_grouped(',', '.')
+        };
+
+        assert_eq!(display("1000.5"), "1,000.5");
+        assert_eq!(display("1234567"), "1,234,567");
+        assert_eq!(display("123456.000001"), "123,456.000001");
+        assert_eq!(display("999.99"), "999.99");
+        assert_eq!(display("1"), "1");
+        assert_eq!(display("0.01"), "0.01");
+
+        let number = Number::new("1000.5").parse().unwrap();
+        assert_eq!(number.display_grouped('.', ','), "1.000,5");
+        assert_eq!(number.display_grouped('\u{202F}', '.'), "1\u{202F}000.5");
+        assert_eq!(number.as_string, "1000.5");
+    }
+
     #[test]
     fn integral_only() {
         let foo = "1";
@@ -97,7 +375,7 @@ mod test_number_sanity {
         assert_eq!(
             parsed,
             Number {
-                as_string: foo,
+                as_string: foo.into(),
                 integral: 1,
                 ..Default::default()
             }
@@ -116,7 +394,7 @@ mod test_number_sanity {
                 integral: 0,
                 fractional: 1,
                 significant_digits_count: 1,
-                as_string: foo,
+                as_string: foo.into(),
                 total_fractional_count: 1,
                 ..Default::default()
             }
@@ -147,7 +425,17 @@ mod test_number_sanity {
 
         let parsed = Number::new(foo).parse();
 
-        assert_eq!(parsed, Err(crate::SolanaPayError::InvalidNumber));
+        assert_eq!(parsed, Err(crate::SolanaPayError::MissingLeadingZero));
+
+        assert_eq!(
+            Number::new(".5").parse(),
+            Err(crate::SolanaPayError::MissingLeadingZero)
+        );
+        assert_eq!(
+            Number::new(".a").parse(),
+            Err(crate::SolanaPayError::InvalidNumber)
+        );
+        assert!(Number::new("0.5").parse().is_ok());
     }
 
     #[test]
@@ -165,7 +453,7 @@ mod test_number_sanity {
 
         let parsed = Number::new(foo).parse();
 
-        assert_eq!(parsed, Err(crate::SolanaPayError::InvalidNumber));
+        assert_eq!(parsed, Err(crate::SolanaPayError::MultipleDecimalPoints));
     }
 
     #[test]
@@ -174,7 +462,7 @@ mod test_number_sanity {
 
         let parsed = Number::new(foo).parse();
 
-        assert_eq!(parsed, Err(crate::SolanaPayError::InvalidNumber));
+        assert_eq!(parsed, Err(crate::SolanaPayError::MultipleDecimalPoints));
     }
 
     #[test]
@@ -202,4 +490,219 @@ mod test_number_sanity {
         assert_eq!(outcome.significant_digits_count, 1);
         assert_eq!(outcome.as_string, "0.001");
     }
+
+    #[test]
+    fn mantissa_scale() {
+        let to_mantissa_scale = |value: &str| {
+            Number::new(value)
+                .parse()
+                .unwrap()
+                .to_mantissa_scale()
+                .unwrap()
+        };
+
+        assert_eq!(to_mantissa_scale("1.5"), (15, 1));
+        assert_eq!(to_mantissa_scale("0.001"), (1, 3));
+        assert_eq!(to_mantissa_scale("100"), (100, 0));
+        assert_eq!(to_mantissa_scale("1.50"), (150, 2));
+
+        assert_eq!(
+            Number::new("18446744073709551615.1")
+                .parse()
+                .unwrap()
+                .to_mantissa_scale(),
+            Err(crate::SolanaPayError::AmountTooLarge)
+        );
+    }
+
+    #[test]
+    fn from_base_units() {
+        let one_and_a_half = Number::from_base_units(1_500_000, 6).unwrap();
+        assert_eq!(one_and_a_half.as_string, "1.5");
+        assert_eq!(one_and_a_half.to_mantissa_scale().unwrap(), (15, 1));
+
+        let zero = Number::from_base_units(0, 6).unwrap();
+        assert_eq!(zero.as_string, "0");
+        assert_eq!(zero, Number::new("0").parse().unwrap());
+
+        assert_eq!(
+            Number::from_base_units(1, 9).unwrap().as_string,
+            "0.000000001"
+        );
+        assert_eq!(Number::from_base_units(42, 0).unwrap().as_string, "42");
+        assert_eq!(
+            Number::from_base_units(u64::MAX, 9).unwrap().as_string,
+            "18446744073.709551615"
+        );
+    }
+
+    #[test]
+    fn display() {
+        for value in ["0", "1", "0.01", "1.50", "100.001"] {
+            let number = Number::new(value).parse().unwrap();
+
+            assert_eq!(format!("{}", number), value);
+            assert_eq!(number.to_string(), value);
+        }
+
+        assert_eq!(
+            Number::from_base_units(1_500_000, 6).unwrap().to_string(),
+            "1.5"
+        );
+    }
+
+    #[test]
+    fn max_string_length() {
+        let at_limit = String::from("0.") + &"0".repeat(crate::MAX_AMOUNT_STR_LEN - 2);
+        assert_eq!(at_limit.len(), crate::MAX_AMOUNT_STR_LEN);
+        assert!(Number::new(&at_limit).parse().is_ok());
+
+        let above_limit = at_limit.clone() + "0";
+        assert_eq!(
+            Number::new(&above_limit).parse(),
+            Err(crate::SolanaPayError::AmountStringTooLong)
+        );
+
+        let megabyte = "1".repeat(1024 * 1024);
+        assert_eq!(
+            Number::new(&megabyte).parse(),
+            Err(crate::SolanaPayError::AmountStringTooLong)
+        );
+    }
+
+    #[test]
+    fn from_f64_rounded() {
+        assert_eq!(Number::from_f64_rounded(1.5, 6).unwrap().as_string, "1.5");
+        assert_eq!(
+            Number::from_f64_rounded(1.5, 6).unwrap(),
+            Number::new("1.5").parse().unwrap()
+        );
+
+        assert_eq!(0.1 + 0.2, 0.30000000000000004);
+        assert_eq!(
+            Number::from_f64_rounded(0.1 + 0.2, 9).unwrap().as_string,
+            "0.3"
+        );
+        assert_eq!(Number::from_f64_rounded(2.0, 9).unwrap().as_string, "2");
+        assert_eq!(Number::from_f64_rounded(0.004, 2).unwrap().as_string, "0");
+        assert_eq!(Number::from_f64_rounded(-0.0, 2).unwrap().as_string, "0");
+
+        assert_eq!(
+            Number::from_f64_rounded(f64::NAN, 9),
+            Err(crate::SolanaPayError::InvalidNumber)
+        );
+        assert_eq!(
+            Number::from_f64_rounded(f64::INFINITY, 9),
+            Err(crate::SolanaPayError::InvalidNumber)
+        );
+        assert_eq!(
+            Number::from_f64_rounded(-1.0, 9),
+            Err(crate::SolanaPayError::InvalidNumber)
+        );
+    }
+
+    #[test]
+    fn percent_not_allowed() {
+        assert_eq!(
+            Number::new("15%").parse(),
+            Err(crate::SolanaPayError::PercentNotAllowed)
+        );
+        assert_eq!(
+            Number::new("0.5%").parse(),
+            Err(crate::SolanaPayError::PercentNotAllowed)
+        );
+        assert_eq!(Number::new("15").parse().unwrap().integral, 15);
+    }
+
+    #[test]
+    fn max_integral_digits() {
+        let at_limit = "1".repeat(crate::MAX_AMOUNT_INTEGRAL_DIGITS);
+        assert_eq!(
+            Number::new(&at_limit).parse().unwrap().integral,
+            11_111_111_111_111_111_111
+        );
+        assert!(Number::new("18446744073709551615.5").parse().is_ok());
+
+        // Within the digit limit but larger than the integral can hold
+        assert_eq!(
+            Number::new("99999999999999999999").parse(),
+            Err(crate::SolanaPayError::AmountTooLarge)
+        );
+
+        let above_limit = at_limit + "1";
+        assert_eq!(
+            Number::new(&above_limit).parse(),
+            Err(crate::SolanaPayError::AmountTooLarge)
+        );
+        assert_eq!(
+            Number::new("99999999999999999999999999.1").parse(),
+            Err(crate::SolanaPayError::AmountTooLarge)
+        );
+    }
+
+    #[test]
+    fn checked_add() {
+        let one_and_a_half = Number::new("1.5").parse().unwrap();
+        let quarter = Number::new("0.25").parse().unwrap();
+
+        let sum = one_and_a_half.checked_add(&quarter).unwrap();
+        assert_eq!(sum.as_string, "1.75");
+        assert_eq!(sum, Number::new("1.75").parse().unwrap());
+        assert_eq!(quarter.checked_add(&one_and_a_half).unwrap(), sum);
+
+        let whole = Number::new("1.25")
+            .parse()
+            .unwrap()
+            .checked_add(&Number::new("0.75").parse().unwrap())
+            .unwrap();
+        assert_eq!(whole.as_string, "2");
+
+        let max = Number::from_base_units(u64::MAX, 0).unwrap();
+        assert_eq!(
+            max.checked_add(&Number::new("1").parse().unwrap()),
+            Err(crate::SolanaPayError::AmountTooLarge)
+        );
+        // Aligning the scale of the max to the scale of `0.1` overflows
+        assert_eq!(
+            max.checked_add(&Number::new("0.1").parse().unwrap()),
+            Err(crate::SolanaPayError::AmountTooLarge)
+        );
+    }
+
+    #[test]
+    fn checked_sub() {
+        let one_and_three_quarters = Number::new("1.75").parse().unwrap();
+        let quarter = Number::new("0.25").parse().unwrap();
+
+        let difference = one_and_three_quarters.checked_sub(&quarter).unwrap();
+        assert_eq!(difference.as_string, "1.5");
+        assert_eq!(
+            quarter.checked_sub(&quarter).unwrap(),
+            Number::new("0").parse().unwrap()
+        );
+
+        assert_eq!(
+            quarter.checked_sub(&one_and_three_quarters),
+            Err(crate::SolanaPayError::NegativeAmountNotAllowed)
+        );
+    }
+
+    #[test]
+    fn normalized() {
+        for (literal, normalized) in [
+            ("0.010", "0.01"),
+            ("1.0", "1"),
+            ("01.50", "1.5"),
+            ("0", "0"),
+            ("100", "100"),
+        ] {
+            let number = Number::new(literal).parse().unwrap();
+
+            assert_eq!(number.normalized().unwrap().as_string, normalized);
+            assert_eq!(
+                number.normalized().unwrap().normalized(),
+                number.normalized()
+            );
+        }
+    }
 }
@@ -0,0 +1,189 @@
+use crate::{PublicKey, QueryParam, MAX_ACCOUNTS_PER_TX};
+
+/// Options that control how a Solana Pay URL is parsed by
+/// [SolanaPayUrl::parse_with_options](crate::SolanaPayUrl::parse_with_options).
+/// The default options parse a URL strictly as defined by the
+/// [Solana Pay Spec](https://docs.solanapay.com/spec).
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// The query parameters that are allowed in the URL,
+    /// `None` allows all query parameters defined by the spec
+    pub allowed_params: Option<Vec<QueryParam>>,
+    /// The maximum number of references allowed in the URL,
+    /// `None` allows up to [MAX_ACCOUNTS_PER_TX] references
+    pub max_references: Option<usize>,
+    /// Skip `reference` query parameters that are not valid Base58 encoded 32 byte arrays
+    /// and collect them in [SolanaPayUrl::invalid_references](crate::SolanaPayUrl::invalid_references)
+    /// instead of failing to parse the whole URL
+    pub lenient_references: bool,
+    /// Collect query parameters that are not defined by the spec in
+    /// [SolanaPayUrl::extra_params](crate::SolanaPayUrl::extra_params)
+    /// instead of failing to parse the whole URL
+    pub lenient_unknown_params: bool,
+    /// Match query parameter keys ignoring their ASCII case
+    pub case_insensitive_params: bool,
+    /// Record the order of the query parameters in
+    /// [SolanaPayUrl::param_order](crate::SolanaPayUrl::param_order)
+    pub preserve_param_order: bool,
+    /// Reject a reference that is the same account as the recipient or the spl-token
+    /// with a [SolanaPayError::ReferenceCollidesWithAccount](crate::SolanaPayError::ReferenceCollidesWithAccount) error
+    pub reject_reference_collisions: bool,
+    /// Reject a reference provided more than once
+    /// with a [SolanaPayError::DuplicateReference](crate::SolanaPayError::DuplicateReference) error
+    pub reject_duplicate_references: bool,
+    /// Accept `spl-token=native` as an alias of the [WRAPPED_SOL_MINT](crate::WRAPPED_SOL_MINT)
+    pub native_spl_token_alias: bool,
+    /// Accept an amount in lamports with a `lamports` or `lamport` suffix
+    pub lamports_amount_suffix: bool,
+    /// Reject an amount with a fractional part with a
+    /// [SolanaPayError::FractionalAmountNotAllowed](crate::SolanaPayError::FractionalAmountNotAllowed) error
+    pub require_integer_amount: bool,
+    /// Require the amount of an SPL Token transfer to be the canonical `uiAmountString`
+    pub canonical_ui_amount: bool,
+    /// Accept a `decimals` query parameter with the decimals of the spl-token mint
+    pub decimals_hint: bool,
+    /// The recipients that are rejected with a
+    /// [SolanaPayError::ForbiddenRecipient](crate::SolanaPayError::ForbiddenRecipient) error
+    pub forbidden_addresses: Vec<PublicKey>,
+}
+
+impl ParseOptions {
+    /// Instantiate the default options
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only allow the query parameters in `allowed_params`, any other query parameter
+    /// results in a [SolanaPayError::ParameterNotAllowed](crate::SolanaPayError::ParameterNotAllowed) error
+    pub fn allowed_params(mut self, allowed_params: &[QueryParam]) -> Self {
+        self.allowed_params.replace(allowed_params.to_vec());
+
+        self
+    }
+
+    /// Only allow up to `max_references` references, this is useful for wallets that
+    /// already use some of the accounts of a transaction for their own instructions.
+    /// The limit can never exceed [MAX_ACCOUNTS_PER_TX]
+    pub fn max_references(mut self, max_references: usize) -> Self {
+        self.max_references.replace(max_references);
+
+        self
+    }
+
+    /// Skip invalid references instead of failing to parse the URL,
+    /// this is useful for watchers that want whatever references they can get
+    pub fn lenient_references(mut self, lenient_references: bool) -> Self {
+        self.lenient_references = lenient_references;
+
+        self
+    }
+
+    /// Keep query parameters that are not defined by the spec, like analytics parameters
+    /// appended by a link shortener, instead of failing to parse the URL.
+    /// They are re-emitted by [SolanaPayUrl::to_url](crate::SolanaPayUrl::to_url)
+    /// so the round trip is lossless
+    pub fn lenient_unknown_params(mut self, lenient_unknown_params: bool) -> Self {
+        self.lenient_unknown_params = lenient_unknown_params;
+
+        self
+    }
+
+    /// Accept query parameter keys like `Amount=` or `SPL-TOKEN=` emitted by some generators.
+    /// The keys are lowercased before they are matched, the spec only defines lowercase keys
+    pub fn case_insensitive_params(mut self, case_insensitive_params: bool) -> Self {
+        self.case_insensitive_params = case_insensitive_params;
+
+        self
+    }
+
+    /// Record the order the query parameters appear in so that
+    /// [SolanaPayUrl::to_url](crate::SolanaPayUrl::to_url) re-emits them in the same order,
+    /// for example for a pass-through proxy that must not reorder the URL.
+    /// The canonical order is used by default
+    pub fn preserve_param_order(mut self, preserve_param_order: bool) -> Self {
+        self.preserve_param_order = preserve_param_order;
+
+        self
+    }
+
+    /// Reject references that collide with the recipient or the spl-token accounts,
+    /// this is opt-in since some flows intentionally reuse these accounts as references
+    pub fn reject_reference_collisions(mut self, reject_reference_collisions: bool) -> Self {
+        self.reject_reference_collisions = reject_reference_collisions;
+
+        self
+    }
+
+    /// Reject duplicate references instead of keeping them, the spec allows duplicates
+    /// but many systems consider a duplicate reference a bug in the code that created the URL
+    pub fn reject_duplicate_references(mut self, reject_duplicate_references: bool) -> Self {
+        self.reject_duplicate_references = reject_duplicate_references;
+
+        self
+    }
+
+    /// Accept the non-standard `spl-token=native` used by some tooling to denote
+    /// SOL as an SPL token, it is parsed as the [WRAPPED_SOL_MINT](crate::WRAPPED_SOL_MINT)
+    pub fn native_spl_token_alias(mut self, native_spl_token_alias: bool) -> Self {
+        self.native_spl_token_alias = native_spl_token_alias;
+
+        self
+    }
+
+    /// Accept the non-standard `amount=1000000000lamports` produced by some backend tools
+    /// that work in lamports. The amount is converted to the canonical SOL amount (`1`)
+    pub fn lamports_amount_suffix(mut self, lamports_amount_suffix: bool) -> Self {
+        self.lamports_amount_suffix = lamports_amount_suffix;
+
+        self
+    }
+
+    /// Only accept whole amounts, for example for a token with 0 decimals like an NFT.
+    /// Any fractional part is rejected, including `1.0`, giving a clearer error than
+    /// the check of the decimals of the mint
+    pub fn require_integer_amount(mut self, require_integer_amount: bool) -> Self {
+        self.require_integer_amount = require_integer_amount;
+
+        self
+    }
+
+    /// Require the amount of an SPL Token transfer to be written exactly as the canonical
+    /// `uiAmountString` of the SPL Token program, which the spec asks for instead of the amount
+    /// in base units. Parsing already rejects an amount with more decimals than the mint,
+    /// like `1.5000001` for a mint with 6 decimals. This also rejects an amount within
+    /// the decimals of the mint that is not canonical, like `1.500000` instead of `1.5`,
+    /// with a [SolanaPayError::NonCanonicalUiAmount](crate::SolanaPayError::NonCanonicalUiAmount) error
+    pub fn canonical_ui_amount(mut self, canonical_ui_amount: bool) -> Self {
+        self.canonical_ui_amount = canonical_ui_amount;
+
+        self
+    }
+
+    /// Accept the non-standard `decimals=6` query parameter as the number of decimals
+    /// of the spl-token mint so that offline tooling can validate the amount without
+    /// looking up the mint. The hint is not part of the Solana Pay Spec, it is metadata that is
+    /// not stored in the [SolanaPayUrl](crate::SolanaPayUrl) and is dropped by
+    /// [SolanaPayUrl::to_url](crate::SolanaPayUrl::to_url). A native SOL amount is always
+    /// validated against 9 decimals
+    pub fn decimals_hint(mut self, decimals_hint: bool) -> Self {
+        self.decimals_hint = decimals_hint;
+
+        self
+    }
+
+    /// Reject a URL whose recipient is one of the `forbidden_addresses`, for example the
+    /// incinerator (`1nc1nerator11111111111111111111111111111111`) to refuse a payment
+    /// that would burn the funds
+    pub fn forbid_addresses(mut self, forbidden_addresses: &[PublicKey]) -> Self {
+        self.forbidden_addresses = forbidden_addresses.to_vec();
+
+        self
+    }
+
+    /// The maximum number of references allowed by these options
+    pub fn references_limit(&self) -> usize {
+        self.max_references
+            .unwrap_or(MAX_ACCOUNTS_PER_TX)
+            .min(MAX_ACCOUNTS_PER_TX)
+    }
+}
@@ -1,11 +1,21 @@
-use std::{borrow::Cow, future::Future};
+use core::fmt;
+use std::{borrow::Cow, collections::HashMap, future::Future};
 
-use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS, NON_ALPHANUMERIC};
 
-use crate::{Number, PublicKey, Reference, SolanaPayError, SolanaPayResult, Utils, SOLANA_SCHEME};
+use crate::{
+    Number, ParseOptions, PublicKey, Reference, References, SolanaPayError, SolanaPayResult, Utils,
+    DEEP_LINK_PARAM, SOLANA_SCHEME,
+};
 
 // TODO Create program derived addresses
 
+/// A handler for a custom query parameter registered with [SolanaPayUrl::parse_with_handlers].
+/// It is called with the value of the query parameter exactly as it appears in the URL,
+/// so the value is still percent-encoded. The handler is `Send` and `Sync` so that
+/// the futures returned by the parse methods can be spawned on a multithreaded runtime
+pub type ParamHandler = Box<dyn Fn(&str) -> SolanaPayResult<()> + Send + Sync>;
+
 /// Structure of a Solana Pay URL.
 /// ***Credit:*** [Solana Pay Docs](https://docs.solanapay.com/spec)
 ///
@@ -17,7 +27,7 @@ use crate::{Number, PublicKey, Reference, SolanaPayError, SolanaPayResult, Utils
 ///     &label=<label>
 ///     &message=<message>
 /// ```
-#[derive(Debug, Default, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Default, Clone)]
 pub struct SolanaPayUrl<'a> {
     /// A single recipient field is required as the pathname.
     /// The value must be the base58-encoded public key of a native SOL account.
@@ -55,7 +65,9 @@ pub struct SolanaPayUrl<'a> {
     /// (IDs usable before knowing the eventual payment transaction).
     /// The `getSignaturesForAddress` RPC method can be used locate transactions this way.
     /// One or multiple references as defined by [Solana Pay Spec](https://docs.solanapay.com/spec#reference).
-    pub references: Vec<Reference>,
+    /// The storage depends on the `smallvec` feature so it is only exposed
+    /// as a slice by [SolanaPayUrl::references]
+    pub(crate) references: References,
     /// A single label field is allowed as an optional query parameter.
     /// The value must be a URL-encoded UTF-8 string that describes the source of the transfer request.
     /// For example, this might be the name of a brand, store, application, or person making the request.
@@ -74,6 +86,46 @@ pub struct SolanaPayUrl<'a> {
     /// instruction of the transaction, immediately before the SOL or SPL Token transfer instruction,
     /// to avoid ambiguity with other instructions in the transaction.
     pub spl_memo: Option<Cow<'a, str>>,
+    /// The values of the `reference` query parameters that are not valid Base58 encoded
+    /// 32 byte arrays. These are only collected when parsing with
+    /// [ParseOptions::lenient_references] enabled, otherwise an invalid reference is an error.
+    pub invalid_references: Vec<Cow<'a, str>>,
+    /// The `(key, value)` pairs of the query parameters that are not defined by the spec,
+    /// exactly as they appear in the URL so they are still percent-encoded.
+    /// These are only collected when parsing with [ParseOptions::lenient_unknown_params]
+    /// enabled and are re-emitted by [SolanaPayUrl::to_url] after the known query parameters
+    pub extra_params: Vec<(Cow<'a, str>, Cow<'a, str>)>,
+    /// The order the query parameters appeared in the parsed URL, a reference or an unknown
+    /// parameter appears once for every occurrence. These are only recorded when parsing with
+    /// [ParseOptions::preserve_param_order] enabled and [SolanaPayUrl::to_url] then
+    /// re-emits the query parameters in this order instead of the canonical order.
+    /// The order does not change the request so it is ignored when comparing or hashing URLs
+    pub param_order: Vec<QueryParam>,
+    /// The inclusive `(min, max)` range in base units (like lamports for native SOL)
+    /// that the amount must fall within, set by [SolanaPayUrl::with_amount_bounds].
+    /// This is a policy of the merchant and is never part of the URL so it is ignored
+    /// when comparing or hashing URLs
+    pub amount_bounds: Option<(u64, u64)>,
+    /// The token program that owns the spl-token mint, which determines the program id
+    /// of the transfer instruction and the derivation of the associated token account.
+    /// It is set explicitly by [SolanaPayUrl::add_token_program] or resolved by
+    /// [SolanaPayUrl::resolve_token_program]. It is never part of the URL so it is ignored
+    /// when comparing or hashing URLs
+    pub token_program: Option<TokenProgramKind>,
+    /// The number of decimals of the spl-token mint. It is set explicitly by
+    /// [SolanaPayUrl::add_mint_decimals] or cached when the mint is looked up while parsing
+    /// a URL with an amount. It is never part of the URL so it is ignored when comparing
+    /// or hashing URLs
+    pub mint_decimals: Option<u8>,
+    /// Emit the normalized form of the amount (see [Number::normalized]) in [SolanaPayUrl::to_url]
+    /// instead of the amount exactly as it was provided, set by [SolanaPayUrl::preserve_amount_literal].
+    /// This only changes how the URL is formatted so it is ignored when comparing or hashing URLs
+    pub normalize_amount: bool,
+    /// Whether the amount was parsed with the `lamports` suffix of
+    /// [ParseOptions::lamports_amount_suffix], which is only valid for native SOL.
+    /// It is kept until all the query params are parsed so that the order of the
+    /// `amount` and `spl-token` params does not matter
+    lamports_amount: bool,
 }
 
 impl<'a> SolanaPayUrl<'a> {
@@ -84,123 +136,421 @@ impl<'a> SolanaPayUrl<'a> {
 
     /// Parse a Solana Pay URL
     pub async fn parse<F: Fn([u8; 32]) -> Fut, Fut: Future<Output = u8> + Send + 'static>(
+        self,
+        solana_pay_url: &'a str,
+        lookup_fn: F,
+    ) -> SolanaPayResult<Self> {
+        self.parse_with_options(solana_pay_url, lookup_fn, &ParseOptions::default())
+            .await
+    }
+
+    /// Parse a Solana Pay URL using the provided [ParseOptions]
+    pub async fn parse_with_options<
+        F: Fn([u8; 32]) -> Fut,
+        Fut: Future<Output = u8> + Send + 'static,
+    >(
+        self,
+        solana_pay_url: &'a str,
+        lookup_fn: F,
+        options: &ParseOptions,
+    ) -> SolanaPayResult<Self> {
+        self.parse_with_options_and_handlers(solana_pay_url, lookup_fn, options, &HashMap::new())
+            .await
+    }
+
+    /// Parse every line of the `input` as a Solana Pay URL, for example a point-of-sale export
+    /// with one URL per line. Lines are trimmed and blank lines are skipped. A result is
+    /// returned for every other line in order so that one invalid line does not abort the batch
+    pub async fn parse_many<F: Fn([u8; 32]) -> Fut, Fut: Future<Output = u8> + Send + 'static>(
+        input: &'a str,
+        lookup_fn: F,
+    ) -> Vec<SolanaPayResult<Self>> {
+        let mut outcome = Vec::<SolanaPayResult<Self>>::new();
+
+        for line in input.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            outcome.push(SolanaPayUrl::new().parse(line, &lookup_fn).await);
+        }
+
+        outcome
+    }
+
+    /// Parse a Solana Pay URL calling the handler registered for each query parameter
+    /// that is not defined by the spec, like an internal `invoice-id`, instead of
+    /// failing with a [SolanaPayError::InvalidQueryParam] error. An error returned by a handler
+    /// fails the parsing. The query parameters defined by the spec are always parsed
+    /// as defined by the spec, a handler registered for one of them is never called.
+    /// A query parameter without a handler is still an error
+    pub async fn parse_with_handlers<
+        F: Fn([u8; 32]) -> Fut,
+        Fut: Future<Output = u8> + Send + 'static,
+    >(
+        self,
+        solana_pay_url: &'a str,
+        lookup_fn: F,
+        handlers: &HashMap<&str, ParamHandler>,
+    ) -> SolanaPayResult<Self> {
+        self.parse_with_options_and_handlers(
+            solana_pay_url,
+            lookup_fn,
+            &ParseOptions::default(),
+            handlers,
+        )
+        .await
+    }
+
+    async fn parse_with_options_and_handlers<
+        F: Fn([u8; 32]) -> Fut,
+        Fut: Future<Output = u8> + Send + 'static,
+    >(
         mut self,
         solana_pay_url: &'a str,
         lookup_fn: F,
+        options: &ParseOptions,
+        handlers: &HashMap<&str, ParamHandler>,
+    ) -> SolanaPayResult<Self> {
+        let decimals_hint = self.parse_syntax(solana_pay_url, options, handlers)?;
+
+        self.resolve(lookup_fn, decimals_hint, options).await
+    }
+
+    // Everything that requires looking up the mint once the syntax is parsed
+    async fn resolve<F: Fn([u8; 32]) -> Fut, Fut: Future<Output = u8> + Send + 'static>(
+        mut self,
+        lookup_fn: F,
+        decimals_hint: Option<u8>,
+        options: &ParseOptions,
     ) -> SolanaPayResult<Self> {
-        if !solana_pay_url.starts_with(SOLANA_SCHEME) {
-            panic!("InvalidSolanaPayScheme");
+        self.resolve_decimals(&lookup_fn, decimals_hint).await?;
+
+        if options.canonical_ui_amount {
+            self.check_canonical_ui_amount()?;
+        }
+
+        Ok(self)
+    }
+
+    // Parses and validates everything that does not require looking up the mint,
+    // returning the decimals hint if one is provided
+    fn parse_syntax(
+        &mut self,
+        solana_pay_url: &'a str,
+        options: &ParseOptions,
+        handlers: &HashMap<&str, ParamHandler>,
+    ) -> SolanaPayResult<Option<u8>> {
+        let (base58_public_key, queries) = Self::split_url(solana_pay_url)?;
+
+        self.parse_segments(base58_public_key, queries.map(Ok), options, handlers)
+    }
+
+    // Parses the recipient and the queries of a URL that is already split,
+    // a query is only converted to a `str` when it is reached so that
+    // [SolanaPayUrl::parse_bytes] fails on the first invalid segment
+    fn parse_segments(
+        &mut self,
+        base58_public_key: &'a str,
+        queries: impl Iterator<Item = SolanaPayResult<&'a str>>,
+        options: &ParseOptions,
+        handlers: &HashMap<&str, ParamHandler>,
+    ) -> SolanaPayResult<Option<u8>> {
+        self.recipient = Self::recipient_key(base58_public_key)?;
+
+        let mut decimals_hint = Option::<u8>::None;
+
+        for query in queries {
+            let query = query?;
+
+            match Self::decimals_hint(query, options) {
+                Some(decimals) => {
+                    decimals_hint.replace(Self::parse_decimals_hint(
+                        query,
+                        decimals,
+                        decimals_hint,
+                    )?);
+                }
+                None => match Self::param_handler(query, handlers) {
+                    Some((handler, value)) => handler(value)?,
+                    None => self.parse_query(query, options)?,
+                },
+            }
         }
 
-        let decoded = solana_pay_url.split(SOLANA_SCHEME).collect::<Vec<&str>>()[1];
+        // Validated once all the query params are parsed so that the order of
+        // the `amount` and `spl-token` params does not matter
+        Self::check_lamports_amount(self.lamports_amount, self.spl_token.as_ref())?;
+        self.validate_accounts(options)?;
 
-        let first_split = if decoded.contains('?') {
-            decoded.split('?').collect::<Vec<&str>>()
-        } else {
-            decoded.split('&').collect::<Vec<&str>>()
+        Ok(decimals_hint)
+    }
+
+    /// Parse a Solana Pay URL from raw bytes, like the payload of a QR code.
+    /// Only the label, message and memo may contain non-ASCII characters and they must be
+    /// valid UTF-8, a [SolanaPayError::InvalidUrlEncodedString] error is returned otherwise.
+    /// The scheme, recipient, query parameter names, amount, spl-token and references
+    /// must be ASCII and a [SolanaPayError::ExpectedAsciiCharacters] error is returned
+    /// if a non-ASCII byte is found in them. The segments of the URL are checked as they are parsed
+    /// instead of validating the whole payload up front, otherwise the URL is parsed exactly like [SolanaPayUrl::parse]
+    pub async fn parse_bytes<F: Fn([u8; 32]) -> Fut, Fut: Future<Output = u8> + Send + 'static>(
+        mut self,
+        solana_pay_url: &'a [u8],
+        lookup_fn: F,
+    ) -> SolanaPayResult<Self> {
+        let options = ParseOptions::default();
+
+        let (base58_public_key, queries) = Self::split_url_bytes(solana_pay_url)?;
+        let decimals_hint = self.parse_segments(
+            Self::ascii_str(base58_public_key)?,
+            queries.map(Self::query_str),
+            &options,
+            &HashMap::new(),
+        )?;
+
+        self.resolve(lookup_fn, decimals_hint, &options).await
+    }
+
+    /// Parse a Solana Pay URL only allowing up to `max` references.
+    /// A URL with more references results in a [SolanaPayError::TooManyReferences] error.
+    /// The limit can never exceed [crate::MAX_ACCOUNTS_PER_TX].
+    pub async fn parse_with_max_references<
+        F: Fn([u8; 32]) -> Fut,
+        Fut: Future<Output = u8> + Send + 'static,
+    >(
+        self,
+        solana_pay_url: &'a str,
+        lookup_fn: F,
+        max: usize,
+    ) -> SolanaPayResult<Self> {
+        let options = ParseOptions::new().max_references(max);
+
+        self.parse_with_options(solana_pay_url, lookup_fn, &options)
+            .await
+    }
+
+    /// Parse a Solana Pay URL only allowing the query parameters in `allowed`.
+    /// A query parameter that is not allowed results in a [SolanaPayError::ParameterNotAllowed] error.
+    /// This is useful for restricted integrations, like a kiosk that only supports
+    /// fixed amount native SOL transfers.
+    pub async fn parse_with_allowed<
+        F: Fn([u8; 32]) -> Fut,
+        Fut: Future<Output = u8> + Send + 'static,
+    >(
+        self,
+        solana_pay_url: &'a str,
+        lookup_fn: F,
+        allowed: &[QueryParam],
+    ) -> SolanaPayResult<Self> {
+        let options = ParseOptions::new().allowed_params(allowed);
+
+        self.parse_with_options(solana_pay_url, lookup_fn, &options)
+            .await
+    }
+
+    /// Parse a Solana Pay URL without failing on the first error.
+    /// Errors in the query parameters are recoverable, the offending parameter is skipped
+    /// and the error is added to the list of errors.
+    /// This is useful for tools that want to report every problem in a URL.
+    /// The [SolanaPayUrl] is `None` if the recipient is invalid since the URL cannot be
+    /// used without it, otherwise it contains all the query parameters that were valid.
+    pub async fn parse_collect_errors<
+        F: Fn([u8; 32]) -> Fut,
+        Fut: Future<Output = u8> + Send + 'static,
+    >(
+        mut self,
+        solana_pay_url: &'a str,
+        lookup_fn: F,
+    ) -> (Option<Self>, Vec<SolanaPayError>) {
+        let mut errors = Vec::<SolanaPayError>::new();
+
+        let (base58_public_key, queries) = match Self::split_url(solana_pay_url) {
+            Ok(split_url) => split_url,
+            Err(error) => return (None, vec![error]),
         };
 
-        if let Some(base58_public_key) = first_split.first() {
-            self.recipient = PublicKey::from_base58(base58_public_key)?;
-        } else {
-            panic!("SolanaPayUrlPartsEmpty");
+        let recipient_is_valid = match Self::base58_chars(Self::trim_recipient(base58_public_key))
+            .and_then(PublicKey::from_base58)
+        {
+            Ok(recipient) => {
+                self.recipient = recipient;
+                true
+            }
+            Err(error) => {
+                errors.push(error);
+                false
+            }
         };
 
-        if first_split.len() > 2 {
-            panic!("TooManySolanaPayUrlParts");
+        for query in queries {
+            if let Err(error) = self.parse_query(query, &ParseOptions::default()) {
+                errors.push(error);
+            }
         }
 
-        let mut queries = Vec::<&str>::new();
-        if let Some(options) = first_split.get(1) {
-            options.split("&").for_each(|value| queries.push(value))
+        // The accounts are compared to the recipient so they are only
+        // validated if the recipient is valid
+        if recipient_is_valid {
+            if let Err(error) = self.validate_accounts(&ParseOptions::default()) {
+                errors.push(error);
+            }
         }
 
-        for query in queries {
-            let split_query = query.split('=').collect::<Vec<&str>>();
-            if split_query.len() != 2 {
-                panic!("InvalidQuery");
-            }
+        if let Err(error) = self.resolve_decimals(&lookup_fn, None).await {
+            errors.push(error);
+        }
 
-            let query_param: QueryParam = split_query[0].try_into()?;
-            let value_to_parse = split_query[1];
-            match query_param {
-                QueryParam::Amount => {
-                    if self.amount.is_some() {
-                        return Err(SolanaPayError::AmountAlreadyExists);
-                    }
-                    self.amount.replace(Number::new(value_to_parse).parse()?);
-                }
+        (recipient_is_valid.then_some(self), errors)
+    }
 
-                QueryParam::SplToken => {
-                    if self.spl_token.is_some() {
-                        return Err(SolanaPayError::SplTokenAlreadyExists);
-                    }
+    /// Parse a Solana Pay URL that may have been percent-encoded as a whole,
+    /// for example when it is embedded in a query parameter of another URL
+    /// (`solana%3Amvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN%3Famount%3D1`).
+    /// The URL is only decoded once and only if the `:` of the scheme is encoded,
+    /// so the label, message and memo of a URL that is not encoded are not decoded twice.
+    pub async fn parse_maybe_encoded<
+        F: Fn([u8; 32]) -> Fut,
+        Fut: Future<Output = u8> + Send + 'static,
+    >(
+        self,
+        solana_pay_url: &'a str,
+        lookup_fn: F,
+    ) -> SolanaPayResult<Self> {
+        let solana_pay_url = Self::strip_bom(solana_pay_url);
 
-                    self.spl_token
-                        .replace(PublicKey::from_base58(value_to_parse)?);
+        let is_encoded = solana_pay_url
+            .get(..ENCODED_SOLANA_SCHEME.len())
+            .map(|scheme| scheme.eq_ignore_ascii_case(ENCODED_SOLANA_SCHEME))
+            .unwrap_or_default();
 
-                    self.resolve_decimals(&lookup_fn).await?;
+        if !is_encoded {
+            return self.parse(solana_pay_url, lookup_fn).await;
+        }
 
-                    // If this is true then the amount is native SOL and therefore
-                    // check the number of decimals don't exceed 9 decimal places
+        let decoded = Utils::url_decode(solana_pay_url)?;
 
-                    if self.amount.is_some()
-                        && self.spl_token.is_none()
-                        && self.amount.as_ref().unwrap().total_fractional_count
-                            > crate::NATIVE_SOL_DECIMAL_COUNT as usize
-                    {
-                        return Err(SolanaPayError::NumberOfDecimalsExceeds9);
-                    }
-                }
+        Ok(SolanaPayUrl::new()
+            .parse(&decoded, lookup_fn)
+            .await?
+            .into_owned())
+    }
 
-                QueryParam::Reference => {
-                    if self.references.len() > crate::MAX_ACCOUNTS_PER_TX {
-                        return Err(SolanaPayError::TooManyReferences);
-                    }
+    /// Extract and parse the Solana Pay URL embedded in the `param` query parameter
+    /// of a universal link, like the links created by [SolanaPayUrl::to_deep_link].
+    /// The embedded URL is percent-decoded and may itself be percent-encoded once more,
+    /// see [SolanaPayUrl::parse_maybe_encoded].
+    pub async fn from_deep_link<
+        F: Fn([u8; 32]) -> Fut,
+        Fut: Future<Output = u8> + Send + 'static,
+    >(
+        link: &str,
+        param: &str,
+        lookup_fn: F,
+    ) -> SolanaPayResult<SolanaPayUrl<'static>> {
+        let embedded = link
+            .split_once('?')
+            .into_iter()
+            .flat_map(|(_, queries)| queries.split('&'))
+            .find_map(|query| {
+                query
+                    .split_once('=')
+                    .filter(|(name, _)| *name == param)
+                    .map(|(_, value)| value)
+            })
+            .ok_or(SolanaPayError::MissingDeepLinkParam)?;
 
-                    self.references
-                        .push(Reference::from_base58(value_to_parse)?)
-                }
+        let decoded = Utils::url_decode(embedded)?;
 
-                QueryParam::Label => {
-                    if self.label.is_some() {
-                        return Err(SolanaPayError::LabelAlreadyExists);
-                    }
+        Ok(SolanaPayUrl::new()
+            .parse_maybe_encoded(&decoded, lookup_fn)
+            .await?
+            .into_owned())
+    }
 
-                    self.label.replace(Utils::url_decode(value_to_parse)?);
-                }
+    /// Parse the query part of a transfer request sent as an
+    /// `application/x-www-form-urlencoded` body, like the body created by
+    /// [SolanaPayUrl::to_form_urlencoded]. The body does not contain the `recipient`
+    /// so it is provided separately, for example by a server that already knows it.
+    pub async fn from_form_urlencoded<
+        F: Fn([u8; 32]) -> Fut,
+        Fut: Future<Output = u8> + Send + 'static,
+    >(
+        recipient: PublicKey,
+        body: &str,
+        lookup_fn: F,
+    ) -> SolanaPayResult<SolanaPayUrl<'static>> {
+        Self::from_form_urlencoded_with_options(
+            recipient,
+            body,
+            lookup_fn,
+            &ParseOptions::default(),
+        )
+        .await
+    }
 
-                QueryParam::Message => {
-                    if self.message.is_some() {
-                        return Err(SolanaPayError::MessageAlreadyExists);
-                    }
+    /// Same as [SolanaPayUrl::from_form_urlencoded] but the body is parsed using the provided
+    /// [ParseOptions], for example to keep the extra parameters or the order of the parameters
+    pub async fn from_form_urlencoded_with_options<
+        F: Fn([u8; 32]) -> Fut,
+        Fut: Future<Output = u8> + Send + 'static,
+    >(
+        recipient: PublicKey,
+        body: &str,
+        lookup_fn: F,
+        options: &ParseOptions,
+    ) -> SolanaPayResult<SolanaPayUrl<'static>> {
+        let mut solana_pay_url = String::from(SOLANA_SCHEME) + &recipient.to_base58();
 
-                    self.message.replace(Utils::url_decode(value_to_parse)?);
-                }
+        for (index, query) in body.split('&').filter(|_| !body.is_empty()).enumerate() {
+            solana_pay_url.push(if index == 0 { '?' } else { '&' });
 
-                QueryParam::SplMemo => {
-                    if self.spl_memo.is_some() {
-                        return Err(SolanaPayError::MemoAlreadyExists);
-                    }
+            // Every value is form decoded and encoded again like [SolanaPayUrl::to_url] encodes it,
+            // a query that is not a `key=value` pair is left for parsing to reject
+            match query.split_once('=') {
+                Some((key, value)) => {
+                    let key = Self::form_decode(key)?;
+                    let value = Self::form_decode(value)?;
 
-                    self.spl_memo.replace(Utils::url_decode(value_to_parse)?);
+                    let encode_set = match Self::query_param(&key, options) {
+                        Ok(QueryParam::Label | QueryParam::Message | QueryParam::SplMemo) => {
+                            NON_ALPHANUMERIC
+                        }
+                        _ => EXTRA_PARAM_ENCODE_SET,
+                    };
+
+                    solana_pay_url.extend(utf8_percent_encode(&key, EXTRA_PARAM_ENCODE_SET));
+                    solana_pay_url.push('=');
+                    solana_pay_url.extend(utf8_percent_encode(&value, encode_set));
                 }
-                QueryParam::Unsupported => return Err(SolanaPayError::InvalidQueryParam),
-            };
+                None => solana_pay_url.push_str(query),
+            }
         }
 
-        Ok(self)
+        Ok(SolanaPayUrl::new()
+            .parse_with_options(&solana_pay_url, lookup_fn, options)
+            .await?
+            .into_owned())
     }
 
-    /// Add a Base58 encoded Ed25519 public key for the recipient
+    /// Add a Base58 encoded Ed25519 public key for the recipient.
+    /// The all-zero public key `11111111111111111111111111111111`, which is the System Program,
+    /// is rejected with a [SolanaPayError::SystemProgramRecipientNotAllowed] error
     pub fn add_recipient(mut self, base58_public_key: &str) -> SolanaPayResult<Self> {
-        let recipient = PublicKey::from_base58(base58_public_key)?;
+        self.recipient = PublicKey::from_base58_checked(base58_public_key)?;
+        Self::check_recipient_not_system_program(&self.recipient)?;
 
-        if !recipient.is_on_ed25519_curve()? {
-            return Err(SolanaPayError::ExpectedRecipientPublicKeyOnCurve);
-        }
+        Ok(self)
+    }
+
+    /// Replace the recipient of an existing URL, for example to reuse a template URL
+    /// for another recipient. The recipient must be on curve like in [SolanaPayUrl::add_recipient]
+    pub fn set_recipient(self, base58_public_key: &str) -> SolanaPayResult<Self> {
+        self.add_recipient(base58_public_key)
+    }
 
-        self.recipient = recipient;
+    /// Same as [SolanaPayUrl::set_recipient] but allows a recipient that is not on curve,
+    /// like a program derived address. Only use this if the recipient is known to be
+    /// able to receive and spend the transfer. The System Program is still rejected
+    /// with a [SolanaPayError::SystemProgramRecipientNotAllowed] error
+    pub fn set_recipient_any(mut self, base58_public_key: &str) -> SolanaPayResult<Self> {
+        self.recipient = PublicKey::from_base58(base58_public_key)?;
+        Self::check_recipient_not_system_program(&self.recipient)?;
 
         Ok(self)
     }
@@ -237,32 +587,61 @@ impl<'a> SolanaPayUrl<'a> {
     /// reference values can be used as client IDs (IDs usable before knowing the eventual payment transaction).
     /// The getSignaturesForAddress RPC method can be used locate transactions this way.
     pub fn add_reference(mut self, base58_reference: &str) -> SolanaPayResult<Self> {
-        if self.references.len() > 254 {
+        let reference = Reference::from_base58(base58_reference)?;
+
+        // Same as calling `dedup` after pushing, since the references are already deduplicated,
+        // without scanning all of them on every call
+        if self.references.last() == Some(&reference) {
+            return Ok(self);
+        }
+
+        if self.remaining_reference_slots() == 0 {
             return Err(SolanaPayError::TooManyReferences);
         }
-        let reference = Reference::from_base58(base58_reference)?;
 
         self.references.push(reference);
 
-        self.references.dedup();
-
         Ok(self)
     }
 
-    /// Same as [SolanaPayUrl::add_reference] above but allows adding multiple references at once
+    /// The references of the URL in the order they were provided
+    pub fn references(&self) -> &[Reference] {
+        &self.references
+    }
+
+    /// The number of references that can still be added before reaching
+    /// [crate::MAX_ACCOUNTS_PER_TX]
+    pub fn remaining_reference_slots(&self) -> usize {
+        crate::MAX_ACCOUNTS_PER_TX.saturating_sub(self.references.len())
+    }
+
+    /// Same as [SolanaPayUrl::add_reference] above but allows adding multiple references at once.
+    /// Adding more references than [SolanaPayUrl::remaining_reference_slots] results in a
+    /// [SolanaPayError::TooManyReferences] error, adjacent duplicates are skipped before
+    /// they are counted
     pub fn add_reference_multiple(mut self, base58_references: &[&str]) -> SolanaPayResult<Self> {
         for base58_reference in base58_references {
             let reference = Reference::from_base58(base58_reference)?;
 
+            // Same as in [SolanaPayUrl::add_reference], skip adjacent duplicates
+            // instead of calling `dedup` on all the references
+            if self.references.last() == Some(&reference) {
+                continue;
+            }
+
+            if self.remaining_reference_slots() == 0 {
+                return Err(SolanaPayError::TooManyReferences);
+            }
+
             self.references.push(reference);
         }
 
-        self.references.dedup();
-
         Ok(self)
     }
 
-    /// Add a UTF-8 URL label
+    /// Add a UTF-8 URL label.
+    /// The label is stored as is and not URL decoded, it is URL encoded by [SolanaPayUrl::to_url],
+    /// so `a%20b` is stored as `a%20b` and not `a b`
     pub fn add_label(mut self, label: &'a str) -> SolanaPayResult<Self> {
         if self.label.is_some() {
             return Err(SolanaPayError::LabelAlreadyExists);
@@ -273,7 +652,21 @@ impl<'a> SolanaPayUrl<'a> {
         Ok(self)
     }
 
-    /// Add a UTF-8 URL message
+    /// Same as [SolanaPayUrl::add_label] but takes an owned label
+    /// so that it is not tied to the lifetime of the URL
+    pub fn add_label_owned(mut self, label: String) -> SolanaPayResult<Self> {
+        if self.label.is_some() {
+            return Err(SolanaPayError::LabelAlreadyExists);
+        }
+
+        self.label.replace(Cow::Owned(label));
+
+        Ok(self)
+    }
+
+    /// Add a UTF-8 URL message.
+    /// The message is stored as is and not URL decoded, it is URL encoded by [SolanaPayUrl::to_url],
+    /// so `a%20b` is stored as `a%20b` and not `a b`
     pub fn add_message(mut self, message: &'a str) -> SolanaPayResult<Self> {
         if self.message.is_some() {
             return Err(SolanaPayError::MessageAlreadyExists);
@@ -284,39 +677,98 @@ impl<'a> SolanaPayUrl<'a> {
         Ok(self)
     }
 
+    /// Same as [SolanaPayUrl::add_message] but takes an owned message
+    /// so that it is not tied to the lifetime of the URL
+    pub fn add_message_owned(mut self, message: String) -> SolanaPayResult<Self> {
+        if self.message.is_some() {
+            return Err(SolanaPayError::MessageAlreadyExists);
+        }
+
+        self.message.replace(Cow::Owned(message));
+
+        Ok(self)
+    }
+
     /// Add a UTF-8 URL memo to be included in the SPL memo part of a transaction
     pub fn add_spl_memo(mut self, spl_memo: &'a str) -> SolanaPayResult<Self> {
         if self.spl_memo.is_some() {
             return Err(SolanaPayError::MemoAlreadyExists);
         }
 
+        Self::check_memo_length(spl_memo.len())?;
+
         self.spl_memo.replace(Cow::Borrowed(spl_memo));
 
         Ok(self)
     }
 
     /// Convert [Self] to a Solana Pay  URL
+    /// The URL is always ASCII since the label, message and memo are percent-encoded,
+    /// so it can be used by QR encoders that only support ASCII
     pub fn to_url(&self) -> String {
-        String::from(SOLANA_SCHEME)
-            + &self.recipient.to_base58()
-            + &self.prepare_amount()
-            + &self.prepare_spl_token()
-            + &self.prepare_references()
-            + &self.prepare_label()
-            + &self.prepare_message()
-            + &self.prepare_spl_memo()
+        let url = if !self.param_order.is_empty() {
+            String::from(SOLANA_SCHEME)
+                + &self.recipient.to_base58()
+                + &self.prepare_params_in_order(QueryEncoding::Url)
+        } else {
+            String::from(SOLANA_SCHEME)
+                + &self.recipient.to_base58()
+                + &self.prepare_amount()
+                + &self.prepare_spl_token()
+                + &self.prepare_references()
+                + &self.prepare_label(QueryEncoding::Url)
+                + &self.prepare_message(QueryEncoding::Url)
+                + &self.prepare_spl_memo(QueryEncoding::Url)
+                + &self.prepare_extra_params(QueryEncoding::Url)
+        };
+
+        debug_assert!(url.is_ascii(), "A Solana Pay URL must be ASCII");
+
+        url
     }
 
-    async fn resolve_decimals<F: Fn([u8; 32]) -> Fut, Fut: Future<Output = u8> + Send + 'static>(
-        &self,
-        lookup_fn: F,
-    ) -> SolanaPayResult<()> {
-        let mint_decimals = lookup_fn(self.spl_token.unwrap().to_bytes()).await; //Unwrap since the spl-token must exist at this point
+    /// Convert [Self] to an `application/x-www-form-urlencoded` body containing only
+    /// the query part of [SolanaPayUrl::to_url], for example `amount=1&label=Michael+Jordan`.
+    /// This is useful for HTTP integrations like a server echoing a transfer request,
+    /// it is not a Solana Pay URL since it has neither the `solana:` scheme nor the recipient.
+    /// Parse it with [SolanaPayUrl::from_form_urlencoded]
+    pub fn to_form_urlencoded(&self) -> String {
+        let query = if !self.param_order.is_empty() {
+            self.prepare_params_in_order(QueryEncoding::Form)
+        } else {
+            self.prepare_amount()
+                + &self.prepare_spl_token()
+                + &self.prepare_references()
+                + &self.prepare_label(QueryEncoding::Form)
+                + &self.prepare_message(QueryEncoding::Form)
+                + &self.prepare_spl_memo(QueryEncoding::Form)
+                + &self.prepare_extra_params(QueryEncoding::Form)
+        };
+
+        query.strip_prefix(['?', '&']).unwrap_or(&query).to_string()
+    }
+
+    /// Clone the amount into a [Number] that owns its string so that it can be stored
+    /// independently of the lifetime of the parsed Solana Pay URL
+    pub fn amount_owned(&self) -> Option<Number<'static>> {
+        self.amount.clone().map(Number::into_owned)
+    }
+
+    /// Whether the wallet must transfer a fixed amount or prompt the user for the amount.
+    /// The spec requires the wallet to prompt the user for the amount if it is not provided.
+    pub fn amount_or_prompt(&self) -> AmountOrPrompt<'_, 'a> {
+        match self.amount.as_ref() {
+            Some(amount) => AmountOrPrompt::Fixed(amount),
+            None => AmountOrPrompt::PromptUser,
+        }
+    }
 
+    /// Check that the number of decimals of the amount does not exceed the `decimals`
+    /// configured by the mint. This allows a URL parsed offline with a default number of decimals
+    /// to be validated later once the actual decimals of the mint are known without parsing it again.
+    pub fn validate_amount_decimals(&self, decimals: u8) -> SolanaPayResult<()> {
         self.amount.as_ref().map_or(Ok(()), |amount_exists| {
-            if (amount_exists.leading_zeroes + amount_exists.significant_digits_count)
-                > mint_decimals as usize
-            {
+            if amount_exists.total_fractional_count > decimals as usize {
                 Err(SolanaPayError::NumberOfDecimalsExceedsMintConfiguration)
             } else {
                 Ok(())
@@ -324,168 +776,3440 @@ impl<'a> SolanaPayUrl<'a> {
         })
     }
 
-    fn prepare_optional_value_with_encoding(
-        &self,
-        name: &str,
-        optional_value: Option<&Cow<str>>,
-    ) -> String {
-        if let Some(value) = optional_value.as_ref() {
-            let encoded = utf8_percent_encode(value, NON_ALPHANUMERIC).to_string();
+    /// Whether [SolanaPayUrl::to_url] emits the amount exactly as it was provided, like `0.010`,
+    /// or its normalized form, like `0.01`. The amount is preserved by default
+    pub fn preserve_amount_literal(mut self, preserve_amount_literal: bool) -> Self {
+        self.normalize_amount = !preserve_amount_literal;
 
-            String::new() + "&" + name + "=" + &encoded
-        } else {
-            String::default()
-        }
+        self
     }
 
-    fn prepare_amount(&self) -> String {
-        if let Some(amount) = self.amount.as_ref() {
-            String::new() + "?" + "amount=" + amount.as_string
-        } else {
-            String::default()
-        }
+    /// Set the token program that owns the spl-token mint when it is already known,
+    /// for example when generating URLs offline for a known mint
+    pub fn add_token_program(mut self, token_program: TokenProgramKind) -> Self {
+        self.token_program.replace(token_program);
+
+        self
     }
 
-    fn prepare_spl_token(&self) -> String {
+    /// Set the number of decimals of the spl-token mint when it is already known,
+    /// for example when generating URLs offline for a known mint
+    pub fn add_mint_decimals(mut self, decimals: u8) -> Self {
+        self.mint_decimals.replace(decimals);
+
+        self
+    }
+
+    /// The `decimals` argument of the `TokenProgram.TransferChecked` instruction of an SPL Token
+    /// transfer, which is the number of decimals of the spl-token mint.
+    /// This is [None] for a native SOL transfer or if the decimals of the mint are not known,
+    /// see [SolanaPayUrl::mint_decimals]
+    pub fn transfer_checked_decimals(&self) -> Option<u8> {
+        self.spl_token.and(self.mint_decimals)
+    }
+
+    /// Resolve the token program of the spl-token mint using the `owner_lookup_fn`
+    /// which fetches the owner of the mint account, for example with an RPC client.
+    /// The token program is left as is if the URL has no spl-token.
+    /// An owner that is neither the Token nor the Token-2022 program results
+    /// in a [SolanaPayError::UnknownTokenProgram] error
+    pub async fn resolve_token_program<
+        F: Fn([u8; 32]) -> Fut,
+        Fut: Future<Output = [u8; 32]> + Send + 'static,
+    >(
+        mut self,
+        owner_lookup_fn: F,
+    ) -> SolanaPayResult<Self> {
         if let Some(spl_token) = self.spl_token.as_ref() {
-            String::new() + "&" + "spl-token=" + &spl_token.to_base58()
-        } else {
-            String::default()
+            let owner = PublicKey(owner_lookup_fn(spl_token.to_bytes()).await);
+
+            let token_program = TokenProgramKind::from_program_id(&owner)
+                .ok_or(SolanaPayError::UnknownTokenProgram)?;
+
+            self.token_program.replace(token_program);
         }
-    }
 
-    fn prepare_references(&self) -> String {
-        let mut outcome = String::default();
+        Ok(self)
+    }
 
-        self.references.iter().for_each(|reference| {
-            outcome.push('&');
-            outcome.push_str("reference=");
-            outcome.push_str(&reference.to_base58());
-        });
+    /// The references as the account metas the wallet must add to the transfer instruction,
+    /// read-only and non-signer in the order they were provided as required by the
+    /// [Solana Pay Spec](https://docs.solanapay.com/spec#reference)
+    #[cfg(feature = "tx")]
+    pub fn reference_account_metas(&self) -> Vec<solana_program::instruction::AccountMeta> {
+        self.references
+            .iter()
+            .map(|reference| {
+                let pubkey = solana_program::pubkey::Pubkey::new_from_array(reference.to_bytes());
 
-        outcome
+                solana_program::instruction::AccountMeta::new_readonly(pubkey, false)
+            })
+            .collect()
     }
 
-    fn prepare_label(&self) -> String {
-        self.prepare_optional_value_with_encoding("label", self.label.as_ref())
+    /// Estimate the number of unique accounts of the payment transaction described by the URL
+    /// following the account layout of the spec, for wallets that budget the accounts
+    /// of a transaction before building it.
+    /// A native SOL transfer uses the payer, the recipient and the System Program.
+    /// An SPL Token `transferChecked` transfer uses the payer, which is also the owner of the source,
+    /// the associated token accounts of the payer and the recipient, the spl-token mint and the token program.
+    /// The recipient wallet itself is not an account of the instruction, only its associated token account is.
+    /// The Memo Program is added if there is a memo and every unique reference is an account.
+    /// Accounts needed to create the associated token account of the recipient are not included
+    pub fn estimated_account_count(&self) -> usize {
+        let transfer_accounts = if self.spl_token.is_some() { 5 } else { 3 };
+        let memo_program = usize::from(self.spl_memo.is_some());
+
+        let unique_references = self
+            .references
+            .iter()
+            .collect::<std::collections::HashSet<&Reference>>()
+            .len();
+
+        transfer_accounts + memo_program + unique_references
     }
 
-    fn prepare_message(&self) -> String {
-        self.prepare_optional_value_with_encoding("message", self.message.as_ref())
+    /// Returns `true` if none of the references are duplicates.
+    /// Parsing does not remove duplicate references so this allows
+    /// callers to check explicitly before broadcasting a transaction
+    pub fn references_unique(&self) -> bool {
+        Self::unique(&self.references)
     }
 
-    fn prepare_spl_memo(&self) -> String {
-        self.prepare_optional_value_with_encoding("memo", self.spl_memo.as_ref())
+    /// Require the amount to be within `min_base_units` and `max_base_units` (inclusive)
+    /// to catch templating bugs that produce absurd amounts.
+    /// The bounds are checked by [SolanaPayUrl::validate] once the decimals of the mint are known
+    pub fn with_amount_bounds(
+        mut self,
+        min_base_units: u64,
+        max_base_units: u64,
+    ) -> SolanaPayResult<Self> {
+        if min_base_units > max_base_units {
+            return Err(SolanaPayError::InvalidAmountBounds);
+        }
+
+        self.amount_bounds.replace((min_base_units, max_base_units));
+
+        Ok(self)
     }
-}
 
-#[derive(Debug, PartialEq, Eq)]
-enum QueryParam {
-    Amount,
-    SplToken,
-    Reference,
-    Label,
-    Message,
-    SplMemo,
-    Unsupported,
-}
+    /// Validate the amount against the `decimals` configured by the mint
+    /// (see [SolanaPayUrl::validate_amount_decimals]) and, if set, the bounds from
+    /// [SolanaPayUrl::with_amount_bounds]. Call this before [SolanaPayUrl::to_url]
+    /// when generating URLs. A URL without an amount is always valid
+    /// since the wallet prompts the user for the amount
+    pub fn validate(&self, decimals: u8) -> SolanaPayResult<()> {
+        self.validate_amount_decimals(decimals)?;
 
-impl TryFrom<&str> for QueryParam {
-    type Error = SolanaPayError;
-    fn try_from(value: &str) -> Result<Self, Self::Error> {
-        let outcome = match value {
-            "amount" => Self::Amount,
-            "spl-token" => Self::SplToken,
-            "reference" => Self::Reference,
-            "label" => Self::Label,
-            "message" => Self::Message,
-            "memo" => Self::SplMemo,
-            _ => Self::Unsupported,
-        };
+        let (amount, (min_base_units, max_base_units)) =
+            match (self.amount.as_ref(), self.amount_bounds) {
+                (Some(amount), Some(amount_bounds)) => (amount, amount_bounds),
+                _ => return Ok(()),
+            };
 
-        Ok(outcome)
+        let (mantissa, scale) = amount.to_mantissa_scale()?;
+        let base_units = 10u64
+            .checked_pow(decimals as u32 - scale)
+            .and_then(|multiplier| mantissa.checked_mul(multiplier))
+            .ok_or(SolanaPayError::AmountTooLarge)?;
+
+        if base_units < min_base_units || base_units > max_base_units {
+            return Err(SolanaPayError::AmountOutOfBounds);
+        }
+
+        Ok(())
     }
-}
 
-#[cfg(test)]
-mod url_parsing_checks {
-    use crate::*;
-    #[test]
-    fn parse_1_sol() {
-        let transfer_1_sol = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1&label=Michael&message=Thanks%20for%20all%20the%20fish&memo=OrderId12345";
-        let lookup_fn = |_value| async { 9 };
-        let transfer_1_sol_decoded = smol::block_on(async {
-            SolanaPayUrl::new()
-                .parse(transfer_1_sol, lookup_fn)
-                .await
-                .unwrap()
-        });
+    /// Wrap the Solana Pay URL in a universal link like `https://solanapay.com/`.
+    /// The URL from [SolanaPayUrl::to_url] is percent-encoded and appended to
+    /// the `base` URL as the [DEEP_LINK_PARAM] (`link`) query parameter,
+    /// for example `https://solanapay.com/?link=solana%3Amvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN`
+    pub fn to_deep_link(&self, base: &str) -> String {
+        let separator = if base.contains('?') { "&" } else { "?" };
 
-        let transfer_1_sol_other = SolanaPayUrl::default()
+        String::from(base) + separator + DEEP_LINK_PARAM + "=" + &Utils::url_encode(&self.to_url())
+    }
+
+    /// Convert [Self] to a human readable Solana Pay URL where the label, message and memo
+    /// are not URL encoded. This is useful for displaying what a wallet will show to a user.
+    /// The output is NOT a valid Solana Pay URL that can be scanned by a wallet,
+    /// use [SolanaPayUrl::to_url] instead.
+    pub fn to_url_display(&self) -> String {
+        String::from(SOLANA_SCHEME)
+            + &self.recipient.to_base58()
+            + &self.prepare_amount()
+            + &self.prepare_spl_token()
+            + &self.prepare_references()
+            + &self.prepare_optional_value_without_encoding("label", self.label.as_ref())
+            + &self.prepare_optional_value_without_encoding("message", self.message.as_ref())
+            + &self.prepare_optional_value_without_encoding("memo", self.spl_memo.as_ref())
+    }
+
+    // Some systems wrap the recipient in quotes or backticks or prefix it with `@`.
+    // Only a single matching pair of wrapping characters is removed
+    // since none of them are valid Base58 characters
+    pub(crate) fn trim_recipient(base58_public_key: &str) -> &str {
+        let base58_public_key = base58_public_key
+            .strip_prefix('@')
+            .unwrap_or(base58_public_key);
+
+        ['"', '\'', '`']
+            .into_iter()
+            .find_map(|wrapping| {
+                base58_public_key
+                    .strip_prefix(wrapping)
+                    .and_then(|unwrapped| unwrapped.strip_suffix(wrapping))
+            })
+            .unwrap_or(base58_public_key)
+    }
+
+    // The recipient as written in the URL, which may be wrapped in quotes
+    pub(crate) fn recipient_key(base58_public_key: &str) -> SolanaPayResult<PublicKey> {
+        PublicKey::from_base58(Self::base58_chars(Self::trim_recipient(base58_public_key))?)
+    }
+
+    // The value of the decimals hint if the query is one and the hint is enabled
+    pub(crate) fn decimals_hint<'q>(query: &'q str, options: &ParseOptions) -> Option<&'q str> {
+        query
+            .strip_prefix(DECIMALS_HINT_PARAM)
+            .filter(|_| options.decimals_hint)
+    }
+
+    // Only a single decimals hint is allowed
+    pub(crate) fn parse_decimals_hint(
+        query: &str,
+        decimals: &str,
+        decimals_hint: Option<u8>,
+    ) -> SolanaPayResult<u8> {
+        decimals
+            .parse::<u8>()
+            .ok()
+            .filter(|_| decimals_hint.is_none())
+            .ok_or_else(|| SolanaPayError::MalformedQuery {
+                param: query.to_string(),
+            })
+    }
+
+    // The spl-token and references are plain Base58 and are never percent-encoded
+    pub(crate) fn unencoded_key(value: &str) -> SolanaPayResult<&str> {
+        if value.contains('%') {
+            return Err(SolanaPayError::UnexpectedEncodingInKeyField);
+        }
+
+        Self::base58_chars(value)
+    }
+
+    // Scanned before decoding so that a character outside the Base58 alphabet is reported
+    // together with its position. A hex encoded public key is left to `PublicKey::from_base58`
+    // which reports it as hex instead of the first `0` it contains
+    pub(crate) fn base58_chars(value: &str) -> SolanaPayResult<&str> {
+        if !PublicKey::looks_like_hex(value) {
+            Utils::check_base58_alphabet(value)?;
+        }
+
+        Ok(value)
+    }
+
+    // The length is the number of UTF-8 bytes and not the number of characters
+    pub(crate) fn check_memo_length(spl_memo_len: usize) -> SolanaPayResult<()> {
+        if spl_memo_len > crate::MAX_MEMO_BYTES {
+            return Err(SolanaPayError::MemoTooLong);
+        }
+
+        Ok(())
+    }
+
+    // QR code payloads occasionally start with a UTF-8 byte order mark
+    pub(crate) fn strip_bom(solana_pay_url: &str) -> &str {
+        solana_pay_url
+            .strip_prefix(UTF8_BOM)
+            .unwrap_or(solana_pay_url)
+    }
+
+    // The scheme is matched case insensitively since some wallets and deep link
+    // handlers uppercase it, the rest of the URL is case sensitive
+    fn strip_scheme(solana_pay_url: &str) -> Option<&str> {
+        solana_pay_url
+            .get(..SOLANA_SCHEME.len())
+            .filter(|scheme| scheme.eq_ignore_ascii_case(SOLANA_SCHEME))
+            .map(|_| &solana_pay_url[SOLANA_SCHEME.len()..])
+    }
+
+    // Same as [SolanaPayUrl::split_url] for raw bytes. The separators are ASCII
+    // and never part of a multi-byte UTF-8 character so the segments are the same
+    fn split_url_bytes(
+        solana_pay_url: &[u8],
+    ) -> SolanaPayResult<(&[u8], impl Iterator<Item = &[u8]>)> {
+        let solana_pay_url = solana_pay_url
+            .strip_prefix(UTF8_BOM.as_bytes())
+            .unwrap_or(solana_pay_url);
+
+        let decoded = solana_pay_url
+            .get(..SOLANA_SCHEME.len())
+            .filter(|scheme| scheme.eq_ignore_ascii_case(SOLANA_SCHEME.as_bytes()))
+            .map(|_| &solana_pay_url[SOLANA_SCHEME.len()..])
+            .ok_or(SolanaPayError::InvalidSolanaPayScheme)?;
+
+        let split_at = |separator: u8| {
+            decoded
+                .iter()
+                .position(|byte| *byte == separator)
+                .map(|index| (&decoded[..index], &decoded[index + 1..]))
+        };
+
+        let (base58_public_key, options) = if let Some((recipient, options)) = split_at(b'?') {
+            if options.contains(&b'?') {
+                return Err(SolanaPayError::TooManySolanaPayUrlParts);
+            }
+
+            (recipient, Some(options))
+        } else if let Some((recipient, options)) = split_at(b'&') {
+            (recipient, Some(options))
+        } else {
+            (decoded, None)
+        };
+
+        let queries = options
+            .into_iter()
+            .flat_map(|options| options.split(|byte| *byte == b'&'));
+
+        Ok((base58_public_key, queries))
+    }
+
+    // Only the values of the label, message and memo are validated as UTF-8,
+    // every other query must be ASCII
+    fn query_str(query: &[u8]) -> SolanaPayResult<&str> {
+        let is_text_query = query
+            .iter()
+            .position(|byte| *byte == b'=')
+            .and_then(|index| Self::ascii_str(&query[..index]).ok())
+            .and_then(|key| QueryParam::try_from(key).ok())
+            .is_some_and(|query_param| {
+                matches!(
+                    query_param,
+                    QueryParam::Label | QueryParam::Message | QueryParam::SplMemo
+                )
+            });
+
+        if is_text_query {
+            std::str::from_utf8(query).map_err(|_| SolanaPayError::InvalidUrlEncodedString)
+        } else {
+            Self::ascii_str(query)
+        }
+    }
+
+    // The conversion of ASCII bytes never fails and only takes the ASCII fast path
+    // of `from_utf8`, it is the only way to get a `str` without unsafe code
+    fn ascii_str(bytes: &[u8]) -> SolanaPayResult<&str> {
+        if !bytes.is_ascii() {
+            return Err(SolanaPayError::ExpectedAsciiCharacters);
+        }
+
+        std::str::from_utf8(bytes).map_err(|_| SolanaPayError::ExpectedAsciiCharacters)
+    }
+
+    pub(crate) fn split_url(
+        solana_pay_url: &str,
+    ) -> SolanaPayResult<(&str, impl Iterator<Item = &str>)> {
+        let solana_pay_url = Self::strip_bom(solana_pay_url);
+
+        let decoded =
+            Self::strip_scheme(solana_pay_url).ok_or(SolanaPayError::InvalidSolanaPayScheme)?;
+
+        let (base58_public_key, options) =
+            if let Some((recipient, options)) = decoded.split_once('?') {
+                if options.contains('?') {
+                    return Err(SolanaPayError::TooManySolanaPayUrlParts);
+                }
+
+                (recipient, Some(options))
+            } else if let Some((recipient, options)) = decoded.split_once('&') {
+                // Without a `?` the recipient is only terminated by the first `&`,
+                // everything after it are query params
+                (recipient, Some(options))
+            } else {
+                (decoded, None)
+            };
+
+        let queries = options.into_iter().flat_map(|options| options.split('&'));
+
+        Ok((base58_public_key, queries))
+    }
+
+    // Splits a query into its key and value
+    pub(crate) fn split_query(query: &str) -> SolanaPayResult<(&str, &str)> {
+        // Only the first `=` separates the key from the value,
+        // any other `=` is part of the value
+        query
+            .split_once('=')
+            .ok_or_else(|| SolanaPayError::MalformedQuery {
+                param: query.to_string(),
+            })
+    }
+
+    // The query parameter of a key, unknown keys are [QueryParam::Unsupported]
+    pub(crate) fn query_param(key: &str, options: &ParseOptions) -> SolanaPayResult<QueryParam> {
+        let query_param = QueryParam::try_from(key)?;
+
+        if options.case_insensitive_params && query_param == QueryParam::Unsupported {
+            // Compared case insensitively instead of lowercasing the key into a new `String`
+            return Ok(QueryParam::SPEC
+                .into_iter()
+                .find(|query_param| query_param.as_str().eq_ignore_ascii_case(key))
+                .unwrap_or(QueryParam::Unsupported));
+        }
+
+        Ok(query_param)
+    }
+
+    fn parse_query(&mut self, query: &'a str, options: &ParseOptions) -> SolanaPayResult<()> {
+        let (key, value_to_parse) = Self::split_query(query)?;
+        let query_param = Self::query_param(key, options)?;
+
+        let references_count = self.references.len();
+
+        if query_param == QueryParam::Unsupported && options.lenient_unknown_params {
+            self.extra_params
+                .push((Cow::Borrowed(key), Cow::Borrowed(value_to_parse)));
+        } else {
+            self.parse_param(query_param, value_to_parse, options)?;
+        }
+
+        // An invalid reference collected by lenient parsing is never re-emitted
+        let is_emitted =
+            query_param != QueryParam::Reference || self.references.len() > references_count;
+
+        if options.preserve_param_order && is_emitted {
+            self.param_order.push(query_param);
+        }
+
+        Ok(())
+    }
+
+    fn parse_param(
+        &mut self,
+        query_param: QueryParam,
+        value_to_parse: &'a str,
+        options: &ParseOptions,
+    ) -> SolanaPayResult<()> {
+        let value_to_parse = Self::trim_value(query_param, value_to_parse);
+        Self::check_param_allowed(query_param, options)?;
+
+        match query_param {
+            QueryParam::Amount => {
+                if self.amount.is_some() {
+                    return Err(SolanaPayError::AmountAlreadyExists);
+                }
+
+                let amount = match Self::lamports_amount(value_to_parse, options)? {
+                    Some(lamports) => {
+                        self.lamports_amount = true;
+
+                        Number::from_base_units(lamports, crate::NATIVE_SOL_DECIMAL_COUNT)?
+                    }
+                    None => Number::new(value_to_parse).parse()?,
+                };
+                Self::check_integer_amount(amount.total_fractional_count != 0, options)?;
+
+                self.amount.replace(amount);
+            }
+
+            QueryParam::SplToken => {
+                if self.spl_token.is_some() {
+                    return Err(SolanaPayError::SplTokenAlreadyExists);
+                }
+
+                self.spl_token
+                    .replace(Self::spl_token_key(value_to_parse, options)?);
+            }
+
+            QueryParam::Reference => {
+                // Invalid references collected by lenient parsing count towards the limit
+                Self::check_references_limit(
+                    self.references
+                        .len()
+                        .saturating_add(self.invalid_references.len()),
+                    options,
+                )?;
+
+                match Self::reference_key(value_to_parse) {
+                    Ok(reference) => self.references.push(reference),
+                    Err(_) if options.lenient_references => {
+                        self.invalid_references.push(Cow::Borrowed(value_to_parse))
+                    }
+                    Err(error) => return Err(error),
+                }
+            }
+
+            QueryParam::Label => {
+                if self.label.is_some() {
+                    return Err(SolanaPayError::LabelAlreadyExists);
+                }
+
+                self.label.replace(Utils::url_decode(value_to_parse)?);
+            }
+
+            QueryParam::Message => {
+                if self.message.is_some() {
+                    return Err(SolanaPayError::MessageAlreadyExists);
+                }
+
+                self.message.replace(Utils::url_decode(value_to_parse)?);
+            }
+
+            QueryParam::SplMemo => {
+                if self.spl_memo.is_some() {
+                    return Err(SolanaPayError::MemoAlreadyExists);
+                }
+
+                let spl_memo = Utils::url_decode(value_to_parse)?;
+                Self::check_memo_length(spl_memo.len())?;
+
+                self.spl_memo.replace(spl_memo);
+            }
+            QueryParam::Unsupported => return Err(SolanaPayError::InvalidQueryParam),
+        };
+
+        Ok(())
+    }
+
+    // Values copied through some channels get surrounding whitespace.
+    // The label, message and memo are not trimmed since whitespace in them
+    // is meaningful and is expected to be percent-encoded
+    pub(crate) fn trim_value(query_param: QueryParam, value: &str) -> &str {
+        match query_param {
+            QueryParam::Label | QueryParam::Message | QueryParam::SplMemo => value,
+            _ => value.trim_matches(|char: char| char.is_ascii_whitespace()),
+        }
+    }
+
+    pub(crate) fn check_param_allowed(
+        query_param: QueryParam,
+        options: &ParseOptions,
+    ) -> SolanaPayResult<()> {
+        if let Some(allowed_params) = options.allowed_params.as_ref() {
+            if query_param != QueryParam::Unsupported && !allowed_params.contains(&query_param) {
+                return Err(SolanaPayError::ParameterNotAllowed(query_param));
+            }
+        }
+
+        Ok(())
+    }
+
+    // The number of lamports of an amount with the `lamports` suffix if the suffix is enabled
+    pub(crate) fn lamports_amount(
+        value: &str,
+        options: &ParseOptions,
+    ) -> SolanaPayResult<Option<u64>> {
+        value
+            .strip_suffix("lamports")
+            .or_else(|| value.strip_suffix("lamport"))
+            .filter(|_| options.lamports_amount_suffix)
+            .map(|lamports| {
+                lamports
+                    .parse::<u64>()
+                    .map_err(|_| SolanaPayError::InvalidNumber)
+            })
+            .transpose()
+    }
+
+    // An amount in lamports is always converted with the decimals of native SOL
+    // so it would silently be in the wrong units for an SPL Token transfer
+    pub(crate) fn check_lamports_amount(
+        lamports_amount: bool,
+        spl_token: Option<&PublicKey>,
+    ) -> SolanaPayResult<()> {
+        if lamports_amount && spl_token.is_some() {
+            return Err(SolanaPayError::LamportsAmountWithSplToken);
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn check_integer_amount(
+        is_fractional: bool,
+        options: &ParseOptions,
+    ) -> SolanaPayResult<()> {
+        if options.require_integer_amount && is_fractional {
+            return Err(SolanaPayError::FractionalAmountNotAllowed);
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn spl_token_key(value: &str, options: &ParseOptions) -> SolanaPayResult<PublicKey> {
+        let spl_token = if options.native_spl_token_alias && value == crate::NATIVE_SPL_TOKEN_ALIAS
+        {
+            crate::WRAPPED_SOL_MINT
+        } else {
+            value
+        };
+
+        PublicKey::from_base58(Self::unencoded_key(spl_token)?)
+    }
+
+    // Checked before decoding so that a URL repeating the `reference` parameter
+    // thousands of times is rejected without decoding every one of them
+    pub(crate) fn check_references_limit(
+        references_count: usize,
+        options: &ParseOptions,
+    ) -> SolanaPayResult<()> {
+        if references_count >= options.references_limit() {
+            return Err(SolanaPayError::TooManyReferences);
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn reference_key(value: &str) -> SolanaPayResult<Reference> {
+        Self::unencoded_key(value).and_then(Reference::from_base58)
+    }
+
+    /// Returns `true` if the spl-token is the [WRAPPED_SOL_MINT](crate::WRAPPED_SOL_MINT).
+    /// The Solana Pay Spec treats wrapped SOL like any other SPL token so the wallet
+    /// still transfers it using the token program, but it always has
+    /// [NATIVE_SOL_DECIMAL_COUNT](crate::NATIVE_SOL_DECIMAL_COUNT) decimals
+    pub fn is_wrapped_sol(&self) -> bool {
+        self.spl_token
+            .as_ref()
+            .map(|spl_token| spl_token.0 == WRAPPED_SOL_MINT_BYTES)
+            .unwrap_or_default()
+    }
+
+    /// Convert [Self] to a [SolanaPayUrl] that owns the amount, label, message and memo
+    /// so that it is no longer tied to the lifetime of the URL it was parsed from.
+    /// This is useful for storing a parsed URL.
+    pub fn into_owned(self) -> SolanaPayUrl<'static> {
+        let into_owned = |value: Cow<str>| Cow::Owned(value.into_owned());
+
+        SolanaPayUrl {
+            recipient: self.recipient,
+            amount: self.amount.map(Number::into_owned),
+            spl_token: self.spl_token,
+            references: self.references,
+            label: self.label.map(into_owned),
+            message: self.message.map(into_owned),
+            spl_memo: self.spl_memo.map(into_owned),
+            invalid_references: self
+                .invalid_references
+                .into_iter()
+                .map(into_owned)
+                .collect(),
+            extra_params: self
+                .extra_params
+                .into_iter()
+                .map(|(key, value)| (into_owned(key), into_owned(value)))
+                .collect(),
+            param_order: self.param_order,
+            amount_bounds: self.amount_bounds,
+            token_program: self.token_program,
+            mint_decimals: self.mint_decimals,
+            normalize_amount: self.normalize_amount,
+            lamports_amount: self.lamports_amount,
+        }
+    }
+
+    /// Returns a canonical owned copy of the URL for systems that dedup payment requests,
+    /// two URLs describing the same payment normalize to equal values.
+    /// The amount is normalized (see [Number::normalized]), duplicate references are removed
+    /// keeping the first occurrence since the order of the references is meaningful
+    /// and empty label, message and memo values are removed since [SolanaPayUrl::to_url] omits them.
+    /// Normalizing a normalized URL returns an equal URL
+    pub fn normalize(&self) -> SolanaPayUrl<'static> {
+        let mut normalized = self.clone().into_owned();
+
+        normalized.amount = self.amount.as_ref().map(|amount| {
+            amount
+                .normalized()
+                .unwrap_or_else(|_| amount.clone().into_owned())
+        });
+
+        let mut seen = std::collections::HashSet::with_capacity(self.references.len());
+        normalized
+            .references
+            .retain(|reference| seen.insert(*reference));
+
+        for value in [
+            &mut normalized.label,
+            &mut normalized.message,
+            &mut normalized.spl_memo,
+        ] {
+            if value.as_ref().is_some_and(|value| value.is_empty()) {
+                value.take();
+            }
+        }
+
+        normalized
+    }
+
+    // The all-zero public key decodes from `11111111111111111111111111111111` and is
+    // the System Program, a transfer to it burns the funds
+    fn check_recipient_not_system_program(recipient: &PublicKey) -> SolanaPayResult<()> {
+        if recipient == &PublicKey::default() {
+            return Err(SolanaPayError::SystemProgramRecipientNotAllowed);
+        }
+
+        Ok(())
+    }
+
+    fn validate_accounts(&self, options: &ParseOptions) -> SolanaPayResult<()> {
+        Self::check_accounts(
+            &self.recipient,
+            self.spl_token.as_ref(),
+            &self.references,
+            options,
+        )
+    }
+
+    pub(crate) fn check_accounts(
+        recipient: &PublicKey,
+        spl_token: Option<&PublicKey>,
+        references: &[Reference],
+        options: &ParseOptions,
+    ) -> SolanaPayResult<()> {
+        // Pasting the mint as the recipient is a common mistake since
+        // the wallet would derive the token account of the mint itself
+        if spl_token == Some(recipient) {
+            return Err(SolanaPayError::RecipientEqualsSplToken);
+        }
+
+        Self::check_recipient_not_system_program(recipient)?;
+
+        if options.forbidden_addresses.contains(recipient) {
+            return Err(SolanaPayError::ForbiddenRecipient);
+        }
+
+        if options.reject_reference_collisions {
+            let collides = |reference: &Reference| {
+                reference.as_bytes() == &recipient.0
+                    || spl_token
+                        .map(|spl_token| reference.as_bytes() == &spl_token.0)
+                        .unwrap_or_default()
+            };
+
+            if references.iter().any(collides) {
+                return Err(SolanaPayError::ReferenceCollidesWithAccount);
+            }
+        }
+
+        if options.reject_duplicate_references && !Self::unique(references) {
+            return Err(SolanaPayError::DuplicateReference);
+        }
+
+        Ok(())
+    }
+
+    // Compares every pair instead of collecting the references into a set so that
+    // checking never allocates, there are at most [crate::MAX_ACCOUNTS_PER_TX] references
+    fn unique(references: &[Reference]) -> bool {
+        references
+            .iter()
+            .enumerate()
+            .all(|(index, reference)| !references[..index].contains(reference))
+    }
+
+    fn param_handler<'q, 'h>(
+        query: &'q str,
+        handlers: &'h HashMap<&str, ParamHandler>,
+    ) -> Option<(&'h ParamHandler, &'q str)> {
+        let (key, value) = query.split_once('=')?;
+
+        if QueryParam::try_from(key) != Ok(QueryParam::Unsupported) {
+            return None;
+        }
+
+        handlers.get(key).map(|handler| (handler, value))
+    }
+
+    // The `uiAmountString` of the SPL Token program has neither trailing zeroes
+    // in the fractional part nor leading zeroes in the integral part
+    fn check_canonical_ui_amount(&self) -> SolanaPayResult<()> {
+        let Some(amount) = self.amount.as_ref().filter(|_| self.spl_token.is_some()) else {
+            return Ok(());
+        };
+
+        if amount.normalized()?.as_string != amount.as_string {
+            return Err(SolanaPayError::NonCanonicalUiAmount);
+        }
+
+        Ok(())
+    }
+
+    async fn resolve_decimals<F: Fn([u8; 32]) -> Fut, Fut: Future<Output = u8> + Send + 'static>(
+        &mut self,
+        lookup_fn: F,
+        decimals_hint: Option<u8>,
+    ) -> SolanaPayResult<()> {
+        if self.is_wrapped_sol() {
+            self.mint_decimals.replace(crate::NATIVE_SOL_DECIMAL_COUNT);
+        }
+
+        if self.amount.is_none() {
+            // The wallet prompts the user for the amount so
+            // there is nothing to validate and no need to lookup the mint
+            return Ok(());
+        }
+
+        if self.is_wrapped_sol() {
+            // The decimals of wrapped SOL are known so there is no need to lookup the mint
+            self.validate_amount_decimals(crate::NATIVE_SOL_DECIMAL_COUNT)
+        } else if let Some(spl_token) = self.spl_token.as_ref() {
+            let mint_decimals = match decimals_hint {
+                Some(decimals) => decimals,
+                None => lookup_fn(spl_token.to_bytes()).await,
+            };
+
+            self.mint_decimals.replace(mint_decimals);
+
+            self.validate_amount_decimals(mint_decimals)
+        } else {
+            // Without an spl-token the amount is native SOL and therefore
+            // the number of decimals must not exceed 9 decimal places
+            self.validate_amount_decimals(crate::NATIVE_SOL_DECIMAL_COUNT)
+                .map_err(|_| SolanaPayError::NumberOfDecimalsExceeds9)
+        }
+    }
+
+    fn prepare_optional_value_with_encoding(
+        &self,
+        name: &str,
+        optional_value: Option<&Cow<str>>,
+        encoding: QueryEncoding,
+    ) -> String {
+        // An empty value carries no information so it is omitted
+        // to produce the minimal URL
+        if let Some(value) = optional_value.filter(|value| !value.is_empty()) {
+            String::new() + "&" + name + "=" + &encoding.encode(value, NON_ALPHANUMERIC)
+        } else {
+            String::default()
+        }
+    }
+
+    fn prepare_optional_value_without_encoding(
+        &self,
+        name: &str,
+        optional_value: Option<&Cow<str>>,
+    ) -> String {
+        if let Some(value) = optional_value.filter(|value| !value.is_empty()) {
+            String::new() + "&" + name + "=" + value
+        } else {
+            String::default()
+        }
+    }
+
+    fn prepare_amount(&self) -> String {
+        if let Some(amount) = self.amount.as_ref() {
+            let normalized = self
+                .normalize_amount
+                .then(|| amount.normalized().ok())
+                .flatten();
+            let amount = normalized.as_ref().unwrap_or(amount);
+
+            String::new() + "?" + "amount=" + &amount.as_string
+        } else {
+            String::default()
+        }
+    }
+
+    fn prepare_spl_token(&self) -> String {
+        if let Some(spl_token) = self.spl_token.as_ref() {
+            String::new() + "&" + "spl-token=" + &spl_token.to_base58()
+        } else {
+            String::default()
+        }
+    }
+
+    fn prepare_references(&self) -> String {
+        let mut outcome = String::default();
+
+        self.references.iter().for_each(|reference| {
+            outcome.push('&');
+            outcome.push_str("reference=");
+            outcome.push_str(&reference.to_base58());
+        });
+
+        outcome
+    }
+
+    fn prepare_label(&self, encoding: QueryEncoding) -> String {
+        self.prepare_optional_value_with_encoding("label", self.label.as_ref(), encoding)
+    }
+
+    fn prepare_message(&self, encoding: QueryEncoding) -> String {
+        self.prepare_optional_value_with_encoding("message", self.message.as_ref(), encoding)
+    }
+
+    fn prepare_spl_memo(&self, encoding: QueryEncoding) -> String {
+        self.prepare_optional_value_with_encoding("memo", self.spl_memo.as_ref(), encoding)
+    }
+
+    // Emits the query parameters in the order recorded in `param_order`. Any query parameter
+    // that was not recorded, like one added after parsing, follows in the canonical order
+    fn prepare_params_in_order(&self, encoding: QueryEncoding) -> String {
+        let prepare_reference =
+            |reference: &Reference| String::from("&reference=") + &reference.to_base58();
+
+        let mut references = self.references.iter();
+        let mut extra_params = self.extra_params.iter();
+
+        let prepare = |query_param: &QueryParam| match query_param {
+            QueryParam::Amount => self.prepare_amount(),
+            QueryParam::SplToken => self.prepare_spl_token(),
+            QueryParam::Label => self.prepare_label(encoding),
+            QueryParam::Message => self.prepare_message(encoding),
+            QueryParam::SplMemo => self.prepare_spl_memo(encoding),
+            QueryParam::Reference | QueryParam::Unsupported => String::default(),
+        };
+
+        let mut outcome = String::default();
+
+        // The amount is prepared with a `?` and the other query parameters with a `&`,
+        // only the first query parameter is separated from the recipient by a `?`
+        let mut push = |prepared: String| {
+            if let Some(query) = prepared.strip_prefix(['?', '&']) {
+                outcome.push(if outcome.is_empty() { '?' } else { '&' });
+                outcome.push_str(query);
+            }
+        };
+
+        for query_param in self.param_order.iter() {
+            match query_param {
+                QueryParam::Reference => {
+                    if let Some(reference) = references.next() {
+                        push(prepare_reference(reference));
+                    }
+                }
+                QueryParam::Unsupported => {
+                    if let Some(extra_param) = extra_params.next() {
+                        push(Self::prepare_extra_param(extra_param, encoding));
+                    }
+                }
+                _ => push(prepare(query_param)),
+            }
+        }
+
+        [
+            QueryParam::Amount,
+            QueryParam::SplToken,
+            QueryParam::Label,
+            QueryParam::Message,
+            QueryParam::SplMemo,
+        ]
+        .iter()
+        .filter(|query_param| !self.param_order.contains(query_param))
+        .for_each(|query_param| push(prepare(query_param)));
+
+        references.for_each(|reference| push(prepare_reference(reference)));
+        extra_params.for_each(|extra_param| push(Self::prepare_extra_param(extra_param, encoding)));
+
+        outcome
+    }
+
+    fn prepare_extra_params(&self, encoding: QueryEncoding) -> String {
+        self.extra_params
+            .iter()
+            .map(|extra_param| Self::prepare_extra_param(extra_param, encoding))
+            .collect()
+    }
+
+    // Captured as they appear in the URL so they are already percent-encoded,
+    // only characters that are never valid in a URL, like non-ASCII characters, are encoded.
+    // A form body carries the text captured from the URL so it is form encoded as is
+    fn prepare_extra_param((key, value): &(Cow<str>, Cow<str>), encoding: QueryEncoding) -> String {
+        String::new()
+            + "&"
+            + &encoding.encode(key, EXTRA_PARAM_ENCODE_SET)
+            + "="
+            + &encoding.encode(value, EXTRA_PARAM_ENCODE_SET)
+    }
+
+    // A `+` is a space in a form body, a literal `+` is always encoded as `%2B`
+    fn form_decode(value: &str) -> SolanaPayResult<String> {
+        Ok(Utils::url_decode(&value.replace('+', " "))?.into_owned())
+    }
+}
+
+impl SolanaPayUrl<'_> {
+    // The fields compared and hashed, values that are never part of the URL
+    // like cached lookups are left out so that a parsed URL equals the same URL
+    // built with the `add_*` methods
+    #[allow(clippy::type_complexity)]
+    fn comparable(
+        &self,
+    ) -> (
+        &PublicKey,
+        &Option<Number<'_>>,
+        &Option<PublicKey>,
+        &References,
+        &Option<Cow<'_, str>>,
+        &Option<Cow<'_, str>>,
+        &Option<Cow<'_, str>>,
+        &Vec<Cow<'_, str>>,
+        &Vec<(Cow<'_, str>, Cow<'_, str>)>,
+    ) {
+        (
+            &self.recipient,
+            &self.amount,
+            &self.spl_token,
+            &self.references,
+            &self.label,
+            &self.message,
+            &self.spl_memo,
+            &self.invalid_references,
+            &self.extra_params,
+        )
+    }
+}
+
+impl PartialEq for SolanaPayUrl<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.comparable() == other.comparable()
+    }
+}
+
+impl Eq for SolanaPayUrl<'_> {}
+
+impl PartialOrd for SolanaPayUrl<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SolanaPayUrl<'_> {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+        self.comparable().cmp(&other.comparable())
+    }
+}
+
+impl core::hash::Hash for SolanaPayUrl<'_> {
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        self.comparable().hash(state)
+    }
+}
+
+/// Iterates over the references of the URL specifically, in the order they were provided.
+/// This is useful for watchers that lookup transactions by their references
+/// ```rust
+/// use solana_payments::SolanaPayUrl;
+///
+/// let url = SolanaPayUrl::new()
+///     .add_reference("82ZJ7nbGpixjeDCmEhUcmwXYfvurzAgGdtSMuHnUgyny")
+///     .unwrap();
+///
+/// for reference in &url {
+///     assert_eq!(reference.to_base58(), "82ZJ7nbGpixjeDCmEhUcmwXYfvurzAgGdtSMuHnUgyny");
+/// }
+/// ```
+impl<'u, 'a> IntoIterator for &'u SolanaPayUrl<'a> {
+    type Item = &'u Reference;
+    type IntoIter = core::slice::Iter<'u, Reference>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.references.iter()
+    }
+}
+
+/// The token program that owns an SPL Token mint
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub enum TokenProgramKind {
+    /// The legacy Token program `TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA`
+    Token,
+    /// The Token-2022 program `TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb`
+    Token2022,
+}
+
+impl TokenProgramKind {
+    /// The Base58 encoded program id of the token program
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Token => "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA",
+            Self::Token2022 => "TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb",
+        }
+    }
+
+    /// The program id of the token program
+    pub fn program_id(&self) -> PublicKey {
+        // The program ids are valid Base58 encoded public keys
+        PublicKey::from_base58(self.as_str()).unwrap_or_default()
+    }
+
+    /// The token program with the `program_id` if it is a known token program
+    pub fn from_program_id(program_id: &PublicKey) -> Option<Self> {
+        [Self::Token, Self::Token2022]
+            .into_iter()
+            .find(|token_program| &token_program.program_id() == program_id)
+    }
+}
+
+/// The amount a wallet must transfer as defined by the
+/// [Solana Pay Spec](https://docs.solanapay.com/spec#amount)
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum AmountOrPrompt<'u, 'a> {
+    /// The URL contains an amount that must be transferred
+    Fixed(&'u Number<'a>),
+    /// The URL does not contain an amount so the wallet must prompt the user for the amount
+    PromptUser,
+}
+
+/// The scheme of a Solana Pay URL whose `:` has been percent-encoded
+const ENCODED_SOLANA_SCHEME: &str = "solana%3A";
+
+const EXTRA_PARAM_ENCODE_SET: &AsciiSet = &CONTROLS.add(b' ');
+
+// Every character other than letters and digits is encoded in a form body
+// except for the space which is encoded as a `+`
+const FORM_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC.remove(b' ');
+
+// How the values of the query parameters are encoded
+#[derive(Debug, Clone, Copy)]
+enum QueryEncoding {
+    // Percent-encoded with the encode set of the query parameter, for [SolanaPayUrl::to_url]
+    Url,
+    // `application/x-www-form-urlencoded`, for [SolanaPayUrl::to_form_urlencoded]
+    Form,
+}
+
+impl QueryEncoding {
+    fn encode(self, value: &str, url_encode_set: &'static AsciiSet) -> String {
+        match self {
+            Self::Url => utf8_percent_encode(value, url_encode_set).to_string(),
+            Self::Form => utf8_percent_encode(value, FORM_ENCODE_SET)
+                .flat_map(str::chars)
+                .map(|char| if char == ' ' { '+' } else { char })
+                .collect(),
+        }
+    }
+}
+
+const DECIMALS_HINT_PARAM: &str = "decimals=";
+
+const UTF8_BOM: &str = "\u{FEFF}";
+
+// The decoded [WRAPPED_SOL_MINT](crate::WRAPPED_SOL_MINT) so that it is compared
+// without encoding the spl-token to a Base58 [String]
+const WRAPPED_SOL_MINT_BYTES: [u8; 32] = [
+    6, 155, 136, 87, 254, 171, 129, 132, 251, 104, 127, 99, 70, 24, 192, 53, 218, 196, 57, 220, 26,
+    235, 59, 85, 152, 160, 240, 0, 0, 0, 0, 1,
+];
+
+/// The query parameters of a Solana Pay URL
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub enum QueryParam {
+    /// The `amount` query parameter
+    Amount,
+    /// The `spl-token` query parameter
+    SplToken,
+    /// The `reference` query parameter
+    Reference,
+    /// The `label` query parameter
+    Label,
+    /// The `message` query parameter
+    Message,
+    /// The `memo` query parameter
+    SplMemo,
+    /// A query parameter not defined by the spec
+    Unsupported,
+}
+
+impl QueryParam {
+    // The query parameters defined by the spec
+    const SPEC: [QueryParam; 6] = [
+        Self::Amount,
+        Self::SplToken,
+        Self::Reference,
+        Self::Label,
+        Self::Message,
+        Self::SplMemo,
+    ];
+
+    /// The name of the query parameter as it appears in a Solana Pay URL
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Amount => "amount",
+            Self::SplToken => "spl-token",
+            Self::Reference => "reference",
+            Self::Label => "label",
+            Self::Message => "message",
+            Self::SplMemo => "memo",
+            Self::Unsupported => "unsupported",
+        }
+    }
+}
+
+impl fmt::Display for QueryParam {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl TryFrom<&str> for QueryParam {
+    type Error = SolanaPayError;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        // All the keys defined by the spec are ASCII so a non-ASCII key is rejected
+        // with a cheap byte scan instead of being treated as an unsupported key
+        if !value.is_ascii() {
+            return Err(SolanaPayError::InvalidQueryParam);
+        }
+
+        let outcome = match value {
+            "amount" => Self::Amount,
+            "spl-token" => Self::SplToken,
+            "reference" => Self::Reference,
+            "label" => Self::Label,
+            "message" => Self::Message,
+            "memo" => Self::SplMemo,
+            _ => Self::Unsupported,
+        };
+
+        Ok(outcome)
+    }
+}
+
+#[cfg(test)]
+mod url_parsing_checks {
+    use crate::*;
+    use std::borrow::Cow;
+    #[test]
+    fn parse_1_sol() {
+        let transfer_1_sol = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1&label=Michael&message=Thanks%20for%20all%20the%20fish&memo=OrderId12345";
+        let lookup_fn = |_value| async { 9 };
+        let transfer_1_sol_decoded = smol::block_on(async {
+            SolanaPayUrl::new()
+                .parse(transfer_1_sol, lookup_fn)
+                .await
+                .unwrap()
+        });
+
+        let transfer_1_sol_other = SolanaPayUrl::default()
+            .add_recipient("mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN")
+            .unwrap()
+            .add_amount("1")
+            .unwrap()
+            .add_label("Michael")
+            .unwrap()
+            .add_message("Thanks for all the fish")
+            .unwrap()
+            .add_spl_memo("OrderId12345")
+            .unwrap();
+
+        assert_eq!(transfer_1_sol_decoded, transfer_1_sol_other);
+
+        let encode_again = transfer_1_sol_decoded.to_url();
+        assert_eq!(&encode_again, transfer_1_sol);
+    }
+
+    #[test]
+    fn parse_spl_token() {
+        let zero_zero_one_usdc = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=0.01&spl-token=EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+        let lookup_fn = |_value| async { 6 };
+
+        let decoded_zero_zero_one_usdc = smol::block_on(async {
+            SolanaPayUrl::new()
+                .parse(zero_zero_one_usdc, lookup_fn)
+                .await
+                .unwrap()
+        });
+
+        let zero_zero_one_usdc_other = SolanaPayUrl::default()
+            .add_recipient("mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN")
+            .unwrap()
+            .add_amount("0.01")
+            .unwrap()
+            .add_spl_token("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v")
+            .unwrap();
+
+        assert_eq!(decoded_zero_zero_one_usdc, zero_zero_one_usdc_other);
+
+        let encode_again = decoded_zero_zero_one_usdc.to_url();
+        assert_eq!(&encode_again, zero_zero_one_usdc);
+    }
+
+    #[test]
+    fn prompt_amount() {
+        let prompt_amount = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN&label=Michael";
+
+        let decoded_prompt_amount = smol::block_on(async {
+            SolanaPayUrl::new()
+                .parse(prompt_amount, Utils::native_sol)
+                .await
+                .unwrap()
+        });
+
+        let prompt_amount_other = SolanaPayUrl::default()
+            .add_recipient("mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN")
+            .unwrap()
+            .add_label("Michael")
+            .unwrap();
+
+        assert_eq!(decoded_prompt_amount, prompt_amount_other);
+
+        let encode_again = decoded_prompt_amount.to_url();
+        assert_eq!(&encode_again, prompt_amount);
+    }
+
+    #[test]
+    fn no_question_mark_multiple_params() {
+        let no_question_mark =
+            "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN&amount=1&label=x";
+
+        let decoded_no_question_mark = smol::block_on(async {
+            SolanaPayUrl::new()
+                .parse(no_question_mark, Utils::native_sol)
+                .await
+                .unwrap()
+        });
+
+        let no_question_mark_other = SolanaPayUrl::default()
+            .add_recipient("mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN")
+            .unwrap()
+            .add_amount("1")
+            .unwrap()
+            .add_label("x")
+            .unwrap();
+
+        assert_eq!(decoded_no_question_mark, no_question_mark_other);
+    }
+
+    #[test]
+    fn to_url_display_is_decoded() {
+        let url = SolanaPayUrl::default()
+            .add_recipient("mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN")
+            .unwrap()
+            .add_amount("1")
+            .unwrap()
+            .add_label("Michael Vines")
+            .unwrap();
+
+        assert_eq!(
+            url.to_url(),
+            "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1&label=Michael%20Vines"
+        );
+        assert_eq!(
+            url.to_url_display(),
+            "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1&label=Michael Vines"
+        );
+    }
+
+    #[test]
+    fn parse_wholesale_encoded() {
+        let url = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1&label=Michael%20Vines&memo=100%25";
+        let encoded = Utils::url_encode(url);
+        assert!(encoded.starts_with("solana%3A"));
+
+        let expected = smol::block_on(async {
+            SolanaPayUrl::new()
+                .parse(url, Utils::native_sol)
+                .await
+                .unwrap()
+        });
+        assert_eq!(expected.label.as_deref(), Some("Michael Vines"));
+        assert_eq!(expected.spl_memo.as_deref(), Some("100%"));
+
+        let decoded_encoded = smol::block_on(async {
+            SolanaPayUrl::new()
+                .parse_maybe_encoded(&encoded, Utils::native_sol)
+                .await
+                .unwrap()
+        });
+        assert_eq!(decoded_encoded, expected);
+
+        let encoded_lowercase = encoded.replace("%3A", "%3a");
+        let decoded_lowercase = smol::block_on(async {
+            SolanaPayUrl::new()
+                .parse_maybe_encoded(&encoded_lowercase, Utils::native_sol)
+                .await
+                .unwrap()
+        });
+        assert_eq!(decoded_lowercase, expected);
+
+        // Not encoded as a whole so the values must only be decoded once
+        let decoded_plain = smol::block_on(async {
+            SolanaPayUrl::new()
+                .parse_maybe_encoded(url, Utils::native_sol)
+                .await
+                .unwrap()
+        });
+        assert_eq!(decoded_plain, expected);
+    }
+
+    #[test]
+    fn collect_all_errors() {
+        let malformed = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1&reference=0OIl&amount=2&label=Michael";
+
+        let (decoded, errors) = smol::block_on(async {
+            SolanaPayUrl::new()
+                .parse_collect_errors(malformed, Utils::native_sol)
+                .await
+        });
+
+        assert_eq!(
+            errors,
+            vec![
+                SolanaPayError::InvalidBase58Character { c: '0', index: 0 },
+                SolanaPayError::AmountAlreadyExists
+            ]
+        );
+
+        let decoded = decoded.unwrap();
+        assert_eq!(decoded.amount.unwrap().as_string, "1");
+        assert!(decoded.references.is_empty());
+        assert_eq!(decoded.label.as_deref(), Some("Michael"));
+
+        let (decoded, errors) = smol::block_on(async {
+            SolanaPayUrl::new()
+                .parse_collect_errors("solana:0OIl?amount=1&amount=2", Utils::native_sol)
+                .await
+        });
+        assert!(decoded.is_none());
+        assert_eq!(
+            errors,
+            vec![
+                SolanaPayError::InvalidBase58Character { c: '0', index: 0 },
+                SolanaPayError::AmountAlreadyExists
+            ]
+        );
+    }
+
+    #[test]
+    fn allowed_params() {
+        let allowed = [QueryParam::Amount, QueryParam::Label];
+
+        let fixed_sol = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1&label=Michael";
+        let decoded = smol::block_on(async {
+            SolanaPayUrl::new()
+                .parse_with_allowed(fixed_sol, Utils::native_sol, &allowed)
+                .await
+        });
+        assert!(decoded.is_ok());
+
+        let with_memo = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1&label=Michael&memo=OrderId12345";
+        let decoded = smol::block_on(async {
+            SolanaPayUrl::new()
+                .parse_with_allowed(with_memo, Utils::native_sol, &allowed)
+                .await
+        });
+        assert_eq!(
+            decoded,
+            Err(SolanaPayError::ParameterNotAllowed(QueryParam::SplMemo))
+        );
+        assert_eq!(
+            decoded.unwrap_err().to_string(),
+            "The query parameter `memo` is not allowed, remove the `memo` parameter"
+        );
+    }
+
+    #[test]
+    fn parse_from_bytes() {
+        let urls = [
+            "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1&label=Michael&message=Thanks%20for%20all%20the%20fish&memo=OrderId12345",
+            "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=0.01&spl-token=EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v&reference=7owWEdgJRWpKsiDFNU4qT2kgMe2kitPXem5Yy8VdNatx",
+            "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN&label=Michael",
+            "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN",
+        ];
+
+        for url in urls {
+            let (from_str, from_bytes) = smol::block_on(async {
+                (
+                    SolanaPayUrl::new().parse(url, |_| async { 6 }).await,
+                    SolanaPayUrl::new()
+                        .parse_bytes(url.as_bytes(), |_| async { 6 })
+                        .await,
+                )
+            });
+
+            assert!(from_str.is_ok());
+            assert_eq!(from_str, from_bytes);
+        }
+
+        let non_utf8_label =
+            b"solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1&label=\xff\xfeMichael";
+        let decoded = smol::block_on(async {
+            SolanaPayUrl::new()
+                .parse_bytes(non_utf8_label, Utils::native_sol)
+                .await
+        });
+        assert_eq!(decoded, Err(SolanaPayError::InvalidUrlEncodedString));
+
+        let utf8_label =
+            "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1&label=Mich\u{e4}el";
+        let decoded = smol::block_on(async {
+            SolanaPayUrl::new()
+                .parse_bytes(utf8_label.as_bytes(), Utils::native_sol)
+                .await
+        });
+        assert_eq!(decoded.unwrap().label.as_deref(), Some("Mich\u{e4}el"));
+
+        let non_ascii_amount = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=\u{ff11}";
+        let decoded = smol::block_on(async {
+            SolanaPayUrl::new()
+                .parse_bytes(non_ascii_amount.as_bytes(), Utils::native_sol)
+                .await
+        });
+        assert_eq!(decoded, Err(SolanaPayError::ExpectedAsciiCharacters));
+
+        let non_ascii_recipient =
+            "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2k\u{e4}?amount=1";
+        let decoded = smol::block_on(async {
+            SolanaPayUrl::new()
+                .parse_bytes(non_ascii_recipient.as_bytes(), Utils::native_sol)
+                .await
+        });
+        assert_eq!(decoded, Err(SolanaPayError::ExpectedAsciiCharacters));
+    }
+
+    #[test]
+    fn parse_malformed_bytes() {
+        let parse_bytes = |url: &[u8]| {
+            smol::block_on(SolanaPayUrl::new().parse_bytes(url, Utils::native_sol)).err()
+        };
+
+        assert_eq!(
+            parse_bytes(b"bitcoin:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN"),
+            Some(SolanaPayError::InvalidSolanaPayScheme)
+        );
+        assert_eq!(
+            parse_bytes(b"\xffsolana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN"),
+            Some(SolanaPayError::InvalidSolanaPayScheme)
+        );
+        assert_eq!(
+            parse_bytes(b"solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1?label=a"),
+            Some(SolanaPayError::TooManySolanaPayUrlParts)
+        );
+        assert_eq!(
+            parse_bytes(b"solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1\xff"),
+            Some(SolanaPayError::ExpectedAsciiCharacters)
+        );
+        assert_eq!(
+            parse_bytes(b"solana:"),
+            smol::block_on(SolanaPayUrl::new().parse("solana:", Utils::native_sol)).err()
+        );
+
+        // The same checks as parsing a string are run
+        let url = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?spl-token=mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN";
+        assert_eq!(
+            parse_bytes(url.as_bytes()),
+            smol::block_on(SolanaPayUrl::new().parse(url, Utils::native_sol)).err()
+        );
+        assert!(parse_bytes(url.as_bytes()).is_some());
+
+        // The segments are checked as they are parsed, so the invalid bytes after
+        // the duplicate amount are never reached
+        assert_eq!(
+            parse_bytes(b"solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1&amount=2&memo=\xff&reference=\xff"),
+            Some(SolanaPayError::AmountAlreadyExists)
+        );
+        assert_eq!(
+            parse_bytes(b"solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1&memo=\xff&reference=\xff"),
+            Some(SolanaPayError::InvalidUrlEncodedString)
+        );
+        assert_eq!(
+            parse_bytes(b"solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1&reference=\xff&memo=\xff"),
+            Some(SolanaPayError::ExpectedAsciiCharacters)
+        );
+    }
+
+    #[test]
+    fn to_deep_link() {
+        let url = SolanaPayUrl::default()
+            .add_recipient("mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN")
+            .unwrap()
+            .add_amount("1")
+            .unwrap()
+            .add_label("Michael Vines")
+            .unwrap();
+
+        let deep_link = url.to_deep_link("https://solanapay.com/");
+        assert_eq!(
+            deep_link,
+            "https://solanapay.com/?link=solana%3Amvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN%3Famount%3D1%26label%3DMichael%2520Vines"
+        );
+
+        let (_, embedded) = deep_link.split_once("?link=").unwrap();
+        assert_eq!(Utils::url_decode(embedded).unwrap(), url.to_url());
+
+        let deep_link = url.to_deep_link("https://phantom.app/ul/browse?ref=shop");
+        let (_, embedded) = deep_link.split_once("&link=").unwrap();
+        assert_eq!(Utils::url_decode(embedded).unwrap(), url.to_url());
+    }
+
+    #[test]
+    fn from_deep_link() {
+        let url = SolanaPayUrl::default()
+            .add_recipient("mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN")
+            .unwrap()
+            .add_amount("1")
+            .unwrap()
+            .add_label("Michael Vines")
+            .unwrap();
+
+        for base in [
+            "https://solanapay.com/",
+            "https://phantom.app/ul/browse?ref=shop",
+        ] {
+            let deep_link = url.to_deep_link(base);
+
+            let decoded = smol::block_on(async {
+                SolanaPayUrl::from_deep_link(&deep_link, DEEP_LINK_PARAM, Utils::native_sol).await
+            });
+            assert_eq!(decoded.unwrap(), url);
+        }
+
+        let encoded_twice = String::from("https://solanapay.com/?link=")
+            + &Utils::url_encode(&Utils::url_encode(&url.to_url()));
+        let decoded = smol::block_on(async {
+            SolanaPayUrl::from_deep_link(&encoded_twice, "link", Utils::native_sol).await
+        });
+        assert_eq!(decoded.unwrap(), url);
+
+        let decoded = smol::block_on(async {
+            SolanaPayUrl::from_deep_link(
+                &url.to_deep_link("https://solanapay.com/"),
+                "url",
+                Utils::native_sol,
+            )
+            .await
+        });
+        assert_eq!(decoded, Err(SolanaPayError::MissingDeepLinkParam));
+    }
+
+    #[test]
+    fn validate_amount_decimals() {
+        let url = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=0.1234567&spl-token=EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+
+        let decoded = smol::block_on(async {
+            SolanaPayUrl::new()
+                .parse(url, Utils::native_sol)
+                .await
+                .unwrap()
+        });
+
+        assert!(decoded.validate_amount_decimals(9).is_ok());
+        assert!(decoded.validate_amount_decimals(7).is_ok());
+        assert_eq!(
+            decoded.validate_amount_decimals(6),
+            Err(SolanaPayError::NumberOfDecimalsExceedsMintConfiguration)
+        );
+
+        let prompt = SolanaPayUrl::new()
+            .add_recipient("mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN")
+            .unwrap();
+        assert!(prompt.validate_amount_decimals(0).is_ok());
+    }
+
+    #[test]
+    fn max_references() {
+        let three_references = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1&reference=7owWEdgJRWpKsiDFNU4qT2kgMe2kitPXem5Yy8VdNatx&reference=7owWEdgJRWpKsiDFNU4qT2kgMe2kitPXem5Yy8VdNaty&reference=7owWEdgJRWpKsiDFNU4qT2kgMe2kitPXem5Yy8VdNatz";
+
+        let decoded = smol::block_on(async {
+            SolanaPayUrl::new()
+                .parse_with_max_references(three_references, Utils::native_sol, 2)
+                .await
+        });
+        assert_eq!(decoded, Err(SolanaPayError::TooManyReferences));
+
+        let decoded = smol::block_on(async {
+            SolanaPayUrl::new()
+                .parse_with_max_references(three_references, Utils::native_sol, 3)
+                .await
+        });
+        assert_eq!(decoded.unwrap().references.len(), 3);
+
+        let at_limit = String::from("solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN")
+            + &"&reference=7owWEdgJRWpKsiDFNU4qT2kgMe2kitPXem5Yy8VdNatx"
+                .repeat(MAX_ACCOUNTS_PER_TX);
+        let decoded = smol::block_on(async {
+            SolanaPayUrl::new()
+                .parse_with_max_references(&at_limit, Utils::native_sol, usize::MAX)
+                .await
+        });
+        assert_eq!(decoded.unwrap().references.len(), MAX_ACCOUNTS_PER_TX);
+
+        let above_limit =
+            at_limit.clone() + "&reference=7owWEdgJRWpKsiDFNU4qT2kgMe2kitPXem5Yy8VdNatx";
+        let decoded = smol::block_on(async {
+            SolanaPayUrl::new()
+                .parse(&above_limit, Utils::native_sol)
+                .await
+        });
+        assert_eq!(decoded, Err(SolanaPayError::TooManyReferences));
+
+        // The references after the limit are invalid so the error proves
+        // that parsing stops before decoding them
+        let far_above_limit = at_limit.clone() + &"&reference=invalid0OIl".repeat(10);
+        let decoded = smol::block_on(async {
+            SolanaPayUrl::new()
+                .parse(&far_above_limit, Utils::native_sol)
+                .await
+        });
+        assert_eq!(decoded, Err(SolanaPayError::TooManyReferences));
+
+        let options = ParseOptions::new().lenient_references(true);
+        let decoded = smol::block_on(async {
+            SolanaPayUrl::new()
+                .parse_with_options(&far_above_limit, Utils::native_sol, &options)
+                .await
+        });
+        assert_eq!(decoded, Err(SolanaPayError::TooManyReferences));
+
+        let mut built = SolanaPayUrl::new();
+        for index in 0..MAX_ACCOUNTS_PER_TX {
+            built = built
+                .add_reference(&Utils::to_base58([index as u8; 32]))
+                .unwrap();
+        }
+        assert_eq!(
+            built.add_reference("7owWEdgJRWpKsiDFNU4qT2kgMe2kitPXem5Yy8VdNatx"),
+            Err(SolanaPayError::TooManyReferences)
+        );
+    }
+
+    #[test]
+    fn into_owned_outlives_input() {
+        let owned = {
+            let short_lived = String::from(
+                "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=0.5&label=Michael&message=Thanks%20for%20all%20the%20fish&memo=OrderId12345",
+            );
+
+            let decoded = smol::block_on(async {
+                SolanaPayUrl::new()
+                    .parse(&short_lived, Utils::native_sol)
+                    .await
+                    .unwrap()
+            });
+
+            decoded.into_owned()
+        };
+
+        assert!(matches!(owned.label, Some(Cow::Owned(_))));
+        assert_eq!(owned.amount.as_ref().unwrap().as_string, "0.5");
+        assert_eq!(owned.label.as_deref(), Some("Michael"));
+        assert_eq!(owned.message.as_deref(), Some("Thanks for all the fish"));
+        assert_eq!(owned.spl_memo.as_deref(), Some("OrderId12345"));
+        assert_eq!(
+            owned.to_url(),
+            "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=0.5&label=Michael&message=Thanks%20for%20all%20the%20fish&memo=OrderId12345"
+        );
+    }
+
+    #[test]
+    fn amount_or_prompt() {
+        let fixed = SolanaPayUrl::default()
+            .add_recipient("mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN")
+            .unwrap()
+            .add_amount("1")
+            .unwrap();
+        assert_eq!(
+            fixed.amount_or_prompt(),
+            AmountOrPrompt::Fixed(&Number::new("1").parse().unwrap())
+        );
+
+        let prompt = SolanaPayUrl::default()
+            .add_recipient("mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN")
+            .unwrap();
+        assert_eq!(prompt.amount_or_prompt(), AmountOrPrompt::PromptUser);
+    }
+
+    #[test]
+    fn amount_and_spl_token_order() {
+        let lookup_fn = |_| async { 6 };
+
+        let amount_first = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=0.0000001&spl-token=EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+        let spl_token_first = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?spl-token=EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v&amount=0.0000001";
+
+        for url in [amount_first, spl_token_first] {
+            let decoded = smol::block_on(async { SolanaPayUrl::new().parse(url, lookup_fn).await });
+            assert_eq!(
+                decoded,
+                Err(SolanaPayError::NumberOfDecimalsExceedsMintConfiguration)
+            );
+
+            let decoded = smol::block_on(async {
+                SolanaPayUrl::new()
+                    .parse_bytes(url.as_bytes(), lookup_fn)
+                    .await
+            });
+            assert_eq!(
+                decoded,
+                Err(SolanaPayError::NumberOfDecimalsExceedsMintConfiguration)
+            );
+
+            let (_, errors) = smol::block_on(async {
+                SolanaPayUrl::new()
+                    .parse_collect_errors(url, lookup_fn)
+                    .await
+            });
+            assert_eq!(
+                errors,
+                vec![SolanaPayError::NumberOfDecimalsExceedsMintConfiguration]
+            );
+        }
+
+        let spl_token_first_valid = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?spl-token=EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v&amount=0.000001";
+        let decoded = smol::block_on(async {
+            SolanaPayUrl::new()
+                .parse(spl_token_first_valid, lookup_fn)
+                .await
+        });
+        assert!(decoded.is_ok());
+    }
+
+    #[test]
+    fn native_sol_and_spl_token_decimals() {
+        let native_sol = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=0.1234567891";
+        let decoded = smol::block_on(async {
+            SolanaPayUrl::new()
+                .parse(native_sol, |_| async { 10 })
+                .await
+        });
+        assert_eq!(decoded, Err(SolanaPayError::NumberOfDecimalsExceeds9));
+
+        let nine_decimals = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=0.123456789";
+        let decoded = smol::block_on(async {
+            SolanaPayUrl::new()
+                .parse(nine_decimals, Utils::native_sol)
+                .await
+        });
+        assert!(decoded.is_ok());
+
+        let spl_token = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=0.1234567891&spl-token=EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+        let decoded =
+            smol::block_on(async { SolanaPayUrl::new().parse(spl_token, |_| async { 10 }).await });
+        assert!(decoded.is_ok());
+    }
+
+    #[test]
+    fn no_lookup_without_amount() {
+        let spl_token_prompt = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?spl-token=EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v&label=Michael";
+
+        let lookup_fn = |_: [u8; 32]| -> std::future::Ready<u8> {
+            panic!("The mint must not be looked up when there is no amount")
+        };
+
+        let decoded = smol::block_on(async {
+            SolanaPayUrl::new()
+                .parse(spl_token_prompt, lookup_fn)
+                .await
+                .unwrap()
+        });
+
+        assert_eq!(decoded.amount_or_prompt(), AmountOrPrompt::PromptUser);
+        assert!(decoded.spl_token.is_some());
+    }
+
+    #[test]
+    fn lenient_references() {
+        let url = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1&reference=7owWEdgJRWpKsiDFNU4qT2kgMe2kitPXem5Yy8VdNatx&reference=0OIl&reference=7owWEdgJRWpKsiDFNU4qT2kgMe2kitPXem5Yy8VdNaty&label=Michael";
+
+        let decoded =
+            smol::block_on(async { SolanaPayUrl::new().parse(url, Utils::native_sol).await });
+        assert_eq!(
+            decoded,
+            Err(SolanaPayError::InvalidBase58Character { c: '0', index: 0 })
+        );
+
+        let options = ParseOptions::new().lenient_references(true);
+        let decoded = smol::block_on(async {
+            SolanaPayUrl::new()
+                .parse_with_options(url, Utils::native_sol, &options)
+                .await
+                .unwrap()
+        });
+
+        assert_eq!(
+            decoded
+                .references
+                .iter()
+                .map(|reference| reference.to_base58())
+                .collect::<Vec<String>>(),
+            vec![
+                "7owWEdgJRWpKsiDFNU4qT2kgMe2kitPXem5Yy8VdNatx",
+                "7owWEdgJRWpKsiDFNU4qT2kgMe2kitPXem5Yy8VdNaty"
+            ]
+        );
+        assert_eq!(decoded.invalid_references, vec!["0OIl"]);
+        assert_eq!(decoded.amount.unwrap().as_string, "1");
+        assert_eq!(decoded.label.as_deref(), Some("Michael"));
+    }
+
+    #[test]
+    fn recipient_equals_spl_token() {
+        let lookup_fn = |_| async { 6 };
+
+        let mint = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+        let url = format!("solana:{mint}?amount=1&spl-token={mint}");
+        let outcome = smol::block_on(SolanaPayUrl::new().parse(&url, lookup_fn));
+        assert_eq!(outcome, Err(SolanaPayError::RecipientEqualsSplToken));
+
+        let url =
+            format!("solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1&spl-token={mint}");
+        let outcome = smol::block_on(SolanaPayUrl::new().parse(&url, lookup_fn));
+        assert!(outcome.is_ok());
+    }
+
+    #[test]
+    fn reference_collides_with_account() {
+        let lookup_fn = |_| async { 9 };
+
+        let url = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1&reference=mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN";
+
+        let options = ParseOptions::new().reject_reference_collisions(true);
+        let outcome =
+            smol::block_on(SolanaPayUrl::new().parse_with_options(url, lookup_fn, &options));
+        assert_eq!(outcome, Err(SolanaPayError::ReferenceCollidesWithAccount));
+
+        // Collisions are allowed by default
+        let outcome = smol::block_on(SolanaPayUrl::new().parse(url, lookup_fn));
+        assert!(outcome.is_ok());
+
+        let url = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1&reference=82ZJ7nbGpixjeDCmEhUcmwXYfvurzAgGdtSMuHnUgyny";
+        let outcome =
+            smol::block_on(SolanaPayUrl::new().parse_with_options(url, lookup_fn, &options));
+        assert!(outcome.is_ok());
+    }
+
+    #[test]
+    fn iterate_references() {
+        let references = [
+            "82ZJ7nbGpixjeDCmEhUcmwXYfvurzAgGdtSMuHnUgyny",
+            "4bXqmGhMHzkbL4YvYuRiLi3Qz6SuR7h9ymW2yXG2H4S7",
+        ];
+        let url = SolanaPayUrl::new()
+            .add_reference_multiple(&references)
+            .unwrap();
+
+        let mut iterated = Vec::<String>::new();
+        for reference in &url {
+            iterated.push(reference.to_base58());
+        }
+        assert_eq!(iterated, references);
+
+        let collected = (&url)
+            .into_iter()
+            .map(Reference::to_base58)
+            .collect::<Vec<String>>();
+        assert_eq!(collected, references);
+    }
+
+    #[test]
+    fn wrapped_sol() {
+        let lookup_fn = |_: [u8; 32]| -> std::future::Ready<u8> {
+            panic!("The decimals of wrapped SOL are known")
+        };
+
+        let url = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=0.000000001&spl-token=So11111111111111111111111111111111111111112";
+        let outcome = smol::block_on(SolanaPayUrl::new().parse(url, lookup_fn)).unwrap();
+        assert!(outcome.is_wrapped_sol());
+
+        let url = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=0.0000000001&spl-token=So11111111111111111111111111111111111111112";
+        let outcome = smol::block_on(SolanaPayUrl::new().parse(url, lookup_fn));
+        assert_eq!(
+            outcome,
+            Err(SolanaPayError::NumberOfDecimalsExceedsMintConfiguration)
+        );
+
+        let url = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1&spl-token=native";
+        let options = ParseOptions::new().native_spl_token_alias(true);
+        let outcome =
+            smol::block_on(SolanaPayUrl::new().parse_with_options(url, lookup_fn, &options))
+                .unwrap();
+        assert!(outcome.is_wrapped_sol());
+        assert_eq!(
+            outcome.spl_token,
+            Some(PublicKey::from_base58(crate::WRAPPED_SOL_MINT).unwrap())
+        );
+        assert_eq!(
+            Utils::from_base58(crate::WRAPPED_SOL_MINT),
+            Ok(super::WRAPPED_SOL_MINT_BYTES)
+        );
+
+        // The alias is not part of the spec so it is rejected by default
+        let outcome = smol::block_on(SolanaPayUrl::new().parse(url, lookup_fn));
+        assert_eq!(outcome, Err(SolanaPayError::InvalidBase58Str));
+    }
+
+    #[test]
+    fn owned_label_and_message() {
+        let url = {
+            let label = String::from("Michael");
+            let message = String::from("Thanks for all the fish");
+
+            SolanaPayUrl::new()
+                .add_label_owned(label)
+                .unwrap()
+                .add_message_owned(message)
+                .unwrap()
+        };
+        assert_eq!(url.label, Some(Cow::Borrowed("Michael")));
+        assert_eq!(url.message, Some(Cow::Borrowed("Thanks for all the fish")));
+        assert_eq!(
+            url.clone().add_label_owned(String::from("Michael")),
+            Err(SolanaPayError::LabelAlreadyExists)
+        );
+
+        // The label is stored as is so encoded characters are encoded again by `to_url`
+        let url = SolanaPayUrl::new().add_label("a%20b").unwrap();
+        assert_eq!(url.label, Some(Cow::Borrowed("a%20b")));
+        assert!(url.to_url().ends_with("label=a%2520b"));
+
+        let url = SolanaPayUrl::new().add_label("a b").unwrap();
+        assert!(url.to_url().ends_with("label=a%20b"));
+    }
+
+    #[test]
+    fn builder_and_parser_parity() {
+        let lookup_fn = |_| async { 6 };
+
+        // (amount, spl-token, references, label, message, memo)
+        type Row<'r> = (
+            Option<&'r str>,
+            Option<&'r str>,
+            &'r [&'r str],
+            Option<&'r str>,
+            Option<&'r str>,
+            Option<&'r str>,
+        );
+
+        let matrix: [Row; 4] = [
+            (None, None, &[], None, None, None),
+            (Some("1"), None, &[], Some("Michael"), None, None),
+            (
+                Some("0.01"),
+                Some("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v"),
+                &["82ZJ7nbGpixjeDCmEhUcmwXYfvurzAgGdtSMuHnUgyny"],
+                Some("a b"),
+                Some("Thanks for all the fish"),
+                Some("OrderId12345"),
+            ),
+            (
+                None,
+                None,
+                &[
+                    "82ZJ7nbGpixjeDCmEhUcmwXYfvurzAgGdtSMuHnUgyny",
+                    "7owWEdgJRWpKsiDFNU4qT2kgMe2kitPXem5Yy8VdNatx",
+                ],
+                Some("Ünïcödé & ?="),
+                None,
+                Some("50%"),
+            ),
+        ];
+
+        for (amount, spl_token, references, label, message, memo) in matrix {
+            let mut built = SolanaPayUrl::new()
+                .add_recipient("mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN")
+                .unwrap()
+                .add_reference_multiple(references)
+                .unwrap();
+            if let Some(amount) = amount {
+                built = built.add_amount(amount).unwrap();
+            }
+            if let Some(spl_token) = spl_token {
+                built = built.add_spl_token(spl_token).unwrap();
+            }
+            if let Some(label) = label {
+                built = built.add_label(label).unwrap();
+            }
+            if let Some(message) = message {
+                built = built.add_message(message).unwrap();
+            }
+            if let Some(memo) = memo {
+                built = built.add_spl_memo(memo).unwrap();
+            }
+
+            let url = built.to_url();
+            let parsed = smol::block_on(SolanaPayUrl::new().parse(&url, lookup_fn)).unwrap();
+
+            assert_eq!(parsed, built);
+            assert_eq!(parsed.to_url(), url);
+        }
+    }
+
+    #[test]
+    fn wrapped_recipient() {
+        let expected =
+            PublicKey::from_base58("mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN").unwrap();
+
+        for url in [
+            "solana:\"mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN\"?amount=1",
+            "solana:'mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN'?amount=1",
+            "solana:`mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN`?amount=1",
+            "solana:@mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1",
+        ] {
+            let parsed = smol::block_on(SolanaPayUrl::new().parse(url, Utils::native_sol)).unwrap();
+            assert_eq!(parsed.recipient, expected);
+
+            let parsed =
+                smol::block_on(SolanaPayUrl::new().parse_bytes(url.as_bytes(), Utils::native_sol))
+                    .unwrap();
+            assert_eq!(parsed.recipient, expected);
+        }
+
+        // Unbalanced wrapping characters are not removed
+        for (url, c) in [
+            (
+                "solana:\"mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1",
+                '"',
+            ),
+            (
+                "solana:\"mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN'?amount=1",
+                '"',
+            ),
+            (
+                "solana:@@mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1",
+                '@',
+            ),
+        ] {
+            let parsed = smol::block_on(SolanaPayUrl::new().parse(url, Utils::native_sol));
+            assert_eq!(
+                parsed,
+                Err(SolanaPayError::InvalidBase58Character { c, index: 0 })
+            );
+        }
+    }
+
+    #[test]
+    fn amount_bounds() {
+        // Between 1 and 10,000 USDC
+        let bounded = |amount| {
+            SolanaPayUrl::new()
+                .add_amount(amount)
+                .unwrap()
+                .with_amount_bounds(1_000_000, 10_000_000_000)
+                .unwrap()
+        };
+
+        assert!(bounded("1").validate(6).is_ok());
+        assert!(bounded("10000").validate(6).is_ok());
+        assert!(bounded("25.5").validate(6).is_ok());
+        assert_eq!(
+            bounded("0.99").validate(6),
+            Err(SolanaPayError::AmountOutOfBounds)
+        );
+        assert_eq!(
+            bounded("10000.000001").validate(6),
+            Err(SolanaPayError::AmountOutOfBounds)
+        );
+        assert_eq!(
+            bounded("1.0000001").validate(6),
+            Err(SolanaPayError::NumberOfDecimalsExceedsMintConfiguration)
+        );
+
+        assert!(SolanaPayUrl::new()
+            .with_amount_bounds(1, 2)
+            .unwrap()
+            .validate(6)
+            .is_ok());
+        assert_eq!(
+            SolanaPayUrl::new().with_amount_bounds(2, 1),
+            Err(SolanaPayError::InvalidAmountBounds)
+        );
+
+        // The bounds are a policy of the merchant and not part of the URL
+        let unbounded = SolanaPayUrl::new().add_amount("1").unwrap();
+        assert_eq!(bounded("1"), unbounded);
+        assert_eq!(
+            std::collections::HashSet::from([bounded("1"), unbounded]).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn reject_duplicate_references() {
+        let duplicates = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?reference=82ZJ7nbGpixjeDCmEhUcmwXYfvurzAgGdtSMuHnUgyny&reference=7owWEdgJRWpKsiDFNU4qT2kgMe2kitPXem5Yy8VdNatx&reference=82ZJ7nbGpixjeDCmEhUcmwXYfvurzAgGdtSMuHnUgyny";
+        let options = ParseOptions::new().reject_duplicate_references(true);
+
+        let parsed = smol::block_on(SolanaPayUrl::new().parse_with_options(
+            duplicates,
+            Utils::native_sol,
+            &options,
+        ));
+        assert_eq!(parsed, Err(SolanaPayError::DuplicateReference));
+
+        // Duplicates are allowed by the spec so they are kept by default
+        let parsed =
+            smol::block_on(SolanaPayUrl::new().parse(duplicates, Utils::native_sol)).unwrap();
+        assert_eq!(parsed.references.len(), 3);
+
+        let unique = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?reference=82ZJ7nbGpixjeDCmEhUcmwXYfvurzAgGdtSMuHnUgyny&reference=7owWEdgJRWpKsiDFNU4qT2kgMe2kitPXem5Yy8VdNatx";
+        let parsed = smol::block_on(SolanaPayUrl::new().parse_with_options(
+            unique,
+            Utils::native_sol,
+            &options,
+        ))
+        .unwrap();
+        assert_eq!(parsed.references.len(), 2);
+    }
+
+    #[test]
+    fn estimated_account_count() {
+        let native_sol = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1";
+        let parsed =
+            smol::block_on(SolanaPayUrl::new().parse(native_sol, Utils::native_sol)).unwrap();
+        let accounts = ["fee payer and sender", "recipient", "System Program"];
+        assert_eq!(parsed.estimated_account_count(), accounts.len());
+
+        let spl_token = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1&spl-token=EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v&reference=82ZJ7nbGpixjeDCmEhUcmwXYfvurzAgGdtSMuHnUgyny&reference=7owWEdgJRWpKsiDFNU4qT2kgMe2kitPXem5Yy8VdNatx&reference=82ZJ7nbGpixjeDCmEhUcmwXYfvurzAgGdtSMuHnUgyny&memo=OrderId12345";
+        let parsed = smol::block_on(SolanaPayUrl::new().parse(spl_token, |_| async { 6 })).unwrap();
+        let accounts = [
+            "fee payer and owner of the source",
+            "source associated token account",
+            "spl-token mint",
+            "destination associated token account",
+            "token program",
+            "Memo Program",
+            "82ZJ7nbGpixjeDCmEhUcmwXYfvurzAgGdtSMuHnUgyny",
+            "7owWEdgJRWpKsiDFNU4qT2kgMe2kitPXem5Yy8VdNatx",
+        ];
+        assert_eq!(parsed.estimated_account_count(), accounts.len());
+    }
+
+    #[test]
+    fn references_unique() {
+        let unique = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?reference=82ZJ7nbGpixjeDCmEhUcmwXYfvurzAgGdtSMuHnUgyny&reference=7owWEdgJRWpKsiDFNU4qT2kgMe2kitPXem5Yy8VdNatx";
+        let parsed = smol::block_on(SolanaPayUrl::new().parse(unique, Utils::native_sol)).unwrap();
+        assert!(parsed.references_unique());
+
+        let duplicates = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?reference=82ZJ7nbGpixjeDCmEhUcmwXYfvurzAgGdtSMuHnUgyny&reference=7owWEdgJRWpKsiDFNU4qT2kgMe2kitPXem5Yy8VdNatx&reference=82ZJ7nbGpixjeDCmEhUcmwXYfvurzAgGdtSMuHnUgyny";
+        let parsed =
+            smol::block_on(SolanaPayUrl::new().parse(duplicates, Utils::native_sol)).unwrap();
+        assert_eq!(parsed.references.len(), 3);
+        assert!(!parsed.references_unique());
+
+        assert!(SolanaPayUrl::new().references_unique());
+    }
+
+    #[test]
+    fn token_program() {
+        let usdc = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?spl-token=EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+        let parsed = smol::block_on(SolanaPayUrl::new().parse(usdc, Utils::native_sol)).unwrap();
+        assert_eq!(parsed.token_program, None);
+
+        for token_program in [TokenProgramKind::Token, TokenProgramKind::Token2022] {
+            let owner_lookup_fn = move |_| async move { token_program.program_id().to_bytes() };
+
+            let resolved =
+                smol::block_on(parsed.clone().resolve_token_program(owner_lookup_fn)).unwrap();
+            assert_eq!(resolved.token_program, Some(token_program));
+
+            let built = parsed.clone().add_token_program(token_program);
+            assert_eq!(built, resolved);
+            assert_eq!(built.to_url(), parsed.to_url());
+            // The token program is not part of the URL
+            assert_eq!(resolved, parsed);
+        }
+
+        let system_program = |_| async { [0u8; 32] };
+        assert_eq!(
+            smol::block_on(parsed.clone().resolve_token_program(system_program)),
+            Err(SolanaPayError::UnknownTokenProgram)
+        );
+
+        // Without an spl-token there is no mint to lookup
+        let native_sol = SolanaPayUrl::new();
+        assert_eq!(
+            smol::block_on(native_sol.resolve_token_program(system_program))
+                .unwrap()
+                .token_program,
+            None
+        );
+    }
+
+    #[test]
+    fn hash_map_key() {
+        use std::collections::HashMap;
+
+        // The label is decoded into an owned `Cow` while the builder borrows it
+        let url = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1&label=Michael%20Jackson&memo=OrderId12345";
+        let parsed = smol::block_on(SolanaPayUrl::new().parse(url, Utils::native_sol)).unwrap();
+        assert!(matches!(parsed.label, Some(Cow::Owned(_))));
+
+        let built = SolanaPayUrl::new()
+            .add_recipient("mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN")
+            .unwrap()
+            .add_amount("1")
+            .unwrap()
+            .add_label("Michael Jackson")
+            .unwrap()
+            .add_spl_memo("OrderId12345")
+            .unwrap();
+        assert!(matches!(built.label, Some(Cow::Borrowed(_))));
+
+        let mut urls = HashMap::<SolanaPayUrl, usize>::new();
+        *urls.entry(parsed).or_default() += 1;
+        *urls.entry(built.clone()).or_default() += 1;
+        *urls.entry(built.into_owned()).or_default() += 1;
+
+        assert_eq!(urls.len(), 1);
+        assert_eq!(urls.into_values().next(), Some(3));
+    }
+
+    #[test]
+    fn lamports_amount_suffix() {
+        let options = ParseOptions::new().lamports_amount_suffix(true);
+        let parse = |url| {
+            smol::block_on(SolanaPayUrl::new().parse_with_options(url, Utils::native_sol, &options))
+        };
+
+        let url = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1000000000lamports";
+        let parsed = parse(url).unwrap();
+        assert_eq!(parsed.amount, Some(Number::new("1").parse().unwrap()));
+        assert_eq!(
+            parsed.to_url(),
+            "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1"
+        );
+
+        let parsed =
+            parse("solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1lamport").unwrap();
+        assert_eq!(parsed.amount.unwrap().as_string, "0.000000001");
+
+        assert_eq!(
+            parse("solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1.5lamports"),
+            Err(SolanaPayError::InvalidNumber)
+        );
+
+        // The suffix is not part of the spec so it is rejected by default
+        let outcome = smol::block_on(SolanaPayUrl::new().parse(url, Utils::native_sol));
+        assert_eq!(outcome, Err(SolanaPayError::InvalidNumber));
+
+        // Lamports are only the base units of native SOL, in any order of the params
+        for url in [
+            "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1000000lamports&spl-token=EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v",
+            "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?spl-token=EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v&amount=1000000lamports",
+        ] {
+            assert_eq!(parse(url), Err(SolanaPayError::LamportsAmountWithSplToken));
+            assert_eq!(
+                SolanaPayUrl::validate_syntax_with_options(url, &options),
+                Err(SolanaPayError::LamportsAmountWithSplToken)
+            );
+        }
+
+        // An amount without the suffix is still in the units of the token
+        let parsed = parse("solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1&spl-token=EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v").unwrap();
+        assert_eq!(parsed.amount, Some(Number::new("1").parse().unwrap()));
+    }
+
+    #[test]
+    fn whitespace_around_values() {
+        let url = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount= 1 &spl-token=\tEPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v &reference= 82ZJ7nbGpixjeDCmEhUcmwXYfvurzAgGdtSMuHnUgyny&label= Michael ";
+        let parsed = smol::block_on(SolanaPayUrl::new().parse(url, |_| async { 6 })).unwrap();
+        assert_eq!(parsed.amount, Some(Number::new("1").parse().unwrap()));
+        assert_eq!(
+            parsed.spl_token,
+            Some(PublicKey::from_base58("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v").unwrap())
+        );
+        assert_eq!(parsed.references.len(), 1);
+        assert_eq!(parsed.label, Some(Cow::Borrowed(" Michael ")));
+
+        // An encoded space is part of the value so it is not trimmed
+        let url = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=%201";
+        let outcome = smol::block_on(SolanaPayUrl::new().parse(url, Utils::native_sol));
+        assert_eq!(outcome, Err(SolanaPayError::InvalidNumber));
+    }
+
+    #[test]
+    fn set_recipient() {
+        let template = SolanaPayUrl::new()
+            .add_recipient("mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN")
+            .unwrap()
+            .add_amount("1")
+            .unwrap()
+            .add_label("Michael")
+            .unwrap();
+
+        let swapped = template
+            .clone()
+            .set_recipient("9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM")
+            .unwrap();
+        assert_eq!(
+            swapped.recipient.to_base58(),
+            "9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM"
+        );
+        assert_eq!(swapped.amount, template.amount);
+        assert_eq!(swapped.label, template.label);
+
+        // A program derived address is off curve
+        let off_curve = "HqAi1JjEEVS6QRvNe7gC4z8pYTuKbWkdZqCuuDpZxxQW";
+        assert_eq!(
+            template.clone().set_recipient(off_curve),
+            Err(SolanaPayError::ExpectedRecipientPublicKeyOnCurve)
+        );
+        assert_eq!(
+            template
+                .clone()
+                .set_recipient_any("11111111111111111111111111111111"),
+            Err(SolanaPayError::SystemProgramRecipientNotAllowed)
+        );
+        assert_eq!(
+            template
+                .set_recipient_any(off_curve)
+                .unwrap()
+                .recipient
+                .to_base58(),
+            off_curve
+        );
+    }
+
+    #[test]
+    fn memo_byte_length() {
+        let fish = "🐟";
+        assert_eq!(fish.len(), 4);
+
+        let at_limit = fish.repeat(crate::MAX_MEMO_BYTES / 4);
+        assert!(SolanaPayUrl::new().add_spl_memo(&at_limit).is_ok());
+
+        // Only 142 characters but 568 bytes
+        let above_limit = fish.repeat(crate::MAX_MEMO_BYTES / 4 + 1);
+        assert_eq!(above_limit.chars().count(), 142);
+        assert_eq!(
+            SolanaPayUrl::new().add_spl_memo(&above_limit),
+            Err(SolanaPayError::MemoTooLong)
+        );
+
+        let url = String::from("solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?memo=")
+            + &Utils::url_encode(&at_limit);
+        let parsed = smol::block_on(SolanaPayUrl::new().parse(&url, Utils::native_sol)).unwrap();
+        assert_eq!(parsed.spl_memo.unwrap(), at_limit);
+
+        // The percent-encoded memo is much longer than the limit, only the decoded bytes count
+        let url = String::from("solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?memo=")
+            + &Utils::url_encode(&above_limit);
+        let outcome = smol::block_on(SolanaPayUrl::new().parse(&url, Utils::native_sol));
+        assert_eq!(outcome, Err(SolanaPayError::MemoTooLong));
+    }
+
+    #[test]
+    fn references_storage() {
+        let references = [
+            "82ZJ7nbGpixjeDCmEhUcmwXYfvurzAgGdtSMuHnUgyny",
+            "7owWEdgJRWpKsiDFNU4qT2kgMe2kitPXem5Yy8VdNatx",
+            "7owWEdgJRWpKsiDFNU4qT2kgMe2kitPXem5Yy8VdNaty",
+            "7owWEdgJRWpKsiDFNU4qT2kgMe2kitPXem5Yy8VdNatz",
+        ];
+        let url = references.iter().fold(
+            String::from("solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1"),
+            |url, reference| url + "&reference=" + reference,
+        );
+
+        let parsed = smol::block_on(SolanaPayUrl::new().parse(&url, Utils::native_sol)).unwrap();
+        let parsed_references = parsed
+            .references()
+            .iter()
+            .map(Reference::to_base58)
+            .collect::<Vec<String>>();
+        assert_eq!(parsed_references, references);
+        assert_eq!(parsed.to_url(), url);
+
+        #[cfg(feature = "smallvec")]
+        assert!(!parsed.references.spilled());
+
+        let url = url + "&reference=4bXqmGhMHzkbL4YvYuRiLi3Qz6SuR7h9ymW2yXG2H4S7";
+        let parsed = smol::block_on(SolanaPayUrl::new().parse(&url, Utils::native_sol)).unwrap();
+        assert_eq!(parsed.references.len(), 5);
+        assert_eq!(parsed.to_url(), url);
+
+        #[cfg(feature = "smallvec")]
+        assert!(parsed.references.spilled());
+    }
+
+    #[test]
+    fn malformed_query() {
+        let url = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount";
+        let expected = || SolanaPayError::MalformedQuery {
+            param: String::from("amount"),
+        };
+
+        let outcome = smol::block_on(SolanaPayUrl::new().parse(url, Utils::native_sol));
+        assert_eq!(outcome, Err(expected()));
+
+        let outcome =
+            smol::block_on(SolanaPayUrl::new().parse_bytes(url.as_bytes(), Utils::native_sol));
+        assert_eq!(outcome, Err(expected()));
+
+        let url = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?label=Michael&amount";
+        let (parsed, errors) =
+            smol::block_on(SolanaPayUrl::new().parse_collect_errors(url, Utils::native_sol));
+        assert_eq!(parsed.unwrap().label, Some(Cow::Borrowed("Michael")));
+        assert_eq!(errors, vec![expected()]);
+    }
+
+    #[test]
+    fn omit_empty_optional_fields() {
+        let recipient_only = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN";
+
+        let url = SolanaPayUrl::new()
             .add_recipient("mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN")
             .unwrap()
-            .add_amount("1")
+            .add_label("")
             .unwrap()
-            .add_label("Michael")
+            .add_message("")
             .unwrap()
-            .add_message("Thanks for all the fish")
+            .add_spl_memo("")
+            .unwrap();
+        assert_eq!(url.to_url(), recipient_only);
+        assert_eq!(url.to_url_display(), recipient_only);
+
+        let parsed = smol::block_on(SolanaPayUrl::new().parse(
+            "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?label=&memo=",
+            Utils::native_sol,
+        ))
+        .unwrap();
+        assert_eq!(parsed.to_url(), recipient_only);
+
+        // Whitespace is meaningful so it is encoded
+        let url = SolanaPayUrl::new().add_label(" ").unwrap();
+        assert!(url.to_url().ends_with("&label=%20"));
+    }
+
+    #[cfg(feature = "tx")]
+    #[test]
+    fn reference_account_metas() {
+        let references = [
+            "82ZJ7nbGpixjeDCmEhUcmwXYfvurzAgGdtSMuHnUgyny",
+            "7owWEdgJRWpKsiDFNU4qT2kgMe2kitPXem5Yy8VdNatx",
+            "4bXqmGhMHzkbL4YvYuRiLi3Qz6SuR7h9ymW2yXG2H4S7",
+        ];
+        let url = SolanaPayUrl::new()
+            .add_reference_multiple(&references)
+            .unwrap();
+
+        let account_metas = url.reference_account_metas();
+        assert_eq!(account_metas.len(), references.len());
+
+        for (account_meta, reference) in account_metas.iter().zip(references) {
+            assert_eq!(account_meta.pubkey.to_string(), reference);
+            assert!(!account_meta.is_signer);
+            assert!(!account_meta.is_writable);
+        }
+
+        assert!(SolanaPayUrl::new().reference_account_metas().is_empty());
+    }
+
+    #[test]
+    fn encoded_key_fields() {
+        let url = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1&spl-token=%45PjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+        let outcome = smol::block_on(SolanaPayUrl::new().parse(url, |_| async { 6 }));
+        assert_eq!(outcome, Err(SolanaPayError::UnexpectedEncodingInKeyField));
+        assert_eq!(
+            SolanaPayUrl::validate_syntax(url),
+            Err(SolanaPayError::UnexpectedEncodingInKeyField)
+        );
+
+        let url = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?reference=82ZJ7nbGpixjeDCmEhUcmwXYfvurzAgGdtSMuHnUgyny%20";
+        let outcome = smol::block_on(SolanaPayUrl::new().parse(url, Utils::native_sol));
+        assert_eq!(outcome, Err(SolanaPayError::UnexpectedEncodingInKeyField));
+    }
+
+    #[test]
+    fn remaining_reference_slots() {
+        let mut url = SolanaPayUrl::new()
+            .add_recipient("mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN")
             .unwrap()
-            .add_spl_memo("OrderId12345")
+            .add_reference("7owWEdgJRWpKsiDFNU4qT2kgMe2kitPXem5Yy8VdNatx")
             .unwrap();
+        assert_eq!(url.remaining_reference_slots(), MAX_ACCOUNTS_PER_TX - 1);
 
-        assert_eq!(transfer_1_sol_decoded, transfer_1_sol_other);
+        // Mutated past the limit by hand
+        for index in 0..=MAX_ACCOUNTS_PER_TX {
+            url.references
+                .push(Reference::from_bytes([index as u8; 32]));
+        }
+        assert!(url.references.len() > MAX_ACCOUNTS_PER_TX);
+        assert_eq!(url.remaining_reference_slots(), 0);
 
-        let encode_again = transfer_1_sol_decoded.to_url();
-        assert_eq!(&encode_again, transfer_1_sol);
+        assert_eq!(
+            url.clone()
+                .add_reference("82ZJ7nbGpixjeDCmEhUcmwXYfvurzAgGdtSMuHnUgyny"),
+            Err(SolanaPayError::TooManyReferences)
+        );
+
+        let mut parsing = url.clone();
+        assert_eq!(
+            parsing.parse_param(
+                QueryParam::Reference,
+                "82ZJ7nbGpixjeDCmEhUcmwXYfvurzAgGdtSMuHnUgyny",
+                &ParseOptions::default()
+            ),
+            Err(SolanaPayError::TooManyReferences)
+        );
     }
 
     #[test]
-    fn parse_spl_token() {
-        let zero_zero_one_usdc = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=0.01&spl-token=EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
-        let lookup_fn = |_value| async { 6 };
+    fn max_references_linear() {
+        let references = (0..MAX_ACCOUNTS_PER_TX)
+            .map(|index| Utils::to_base58([index as u8; 32]))
+            .collect::<Vec<String>>();
 
-        let decoded_zero_zero_one_usdc = smol::block_on(async {
-            SolanaPayUrl::new()
-                .parse(&zero_zero_one_usdc, lookup_fn)
-                .await
+        let recipient = SolanaPayUrl::new()
+            .add_recipient("mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN")
+            .unwrap();
+        let built = references.iter().fold(recipient, |url, reference| {
+            // Adjacent duplicates are removed like before
+            url.add_reference(reference)
+                .unwrap()
+                .add_reference(reference)
                 .unwrap()
         });
+        assert_eq!(built.references.len(), MAX_ACCOUNTS_PER_TX);
 
-        let zero_zero_one_usdc_other = SolanaPayUrl::default()
+        let url = built.to_url();
+        let parsed = smol::block_on(SolanaPayUrl::new().parse(&url, Utils::native_sol)).unwrap();
+        let parsed_references = parsed
+            .references
+            .iter()
+            .map(Reference::to_base58)
+            .collect::<Vec<String>>();
+        assert_eq!(parsed_references, references);
+        assert!(parsed.references_unique());
+    }
+
+    #[test]
+    fn byte_order_mark() {
+        let url =
+            "\u{FEFF}solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1&label=Michael";
+
+        let parsed = smol::block_on(SolanaPayUrl::new().parse(url, Utils::native_sol)).unwrap();
+        assert_eq!(parsed.amount, Some(Number::new("1").parse().unwrap()));
+        assert_eq!(
+            parsed.to_url(),
+            "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1&label=Michael"
+        );
+
+        let parsed_bytes =
+            smol::block_on(SolanaPayUrl::new().parse_bytes(url.as_bytes(), Utils::native_sol))
+                .unwrap();
+        assert_eq!(parsed_bytes, parsed);
+
+        let encoded = "\u{FEFF}solana%3Amvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN%3Famount%3D1%26label%3DMichael";
+        let parsed_encoded =
+            smol::block_on(SolanaPayUrl::new().parse_maybe_encoded(encoded, Utils::native_sol))
+                .unwrap();
+        assert_eq!(parsed_encoded, parsed);
+
+        // Spans are offsets into the input including the byte order mark
+        let spans = SolanaPayUrl::parse_spans(url).unwrap();
+        assert_eq!(
+            &url[spans.recipient],
+            "mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN"
+        );
+        assert!(SolanaPayUrl::validate_syntax(url).is_ok());
+    }
+
+    #[test]
+    fn preserve_amount_literal() {
+        let url = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=0.010";
+        let parsed = smol::block_on(SolanaPayUrl::new().parse(url, Utils::native_sol)).unwrap();
+        assert_eq!(parsed.to_url(), url);
+
+        let preserved = parsed.clone().preserve_amount_literal(true);
+        assert_eq!(preserved.to_url(), url);
+
+        let normalized = parsed.clone().preserve_amount_literal(false);
+        // The flag only changes the formatting so it is the same URL
+        assert_eq!(normalized, parsed);
+        assert_eq!(
+            normalized.to_url(),
+            "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=0.01"
+        );
+        assert_eq!(normalized.amount.unwrap().as_string, "0.010");
+    }
+
+    #[test]
+    fn decimals_hint() {
+        let lookup_fn = |_: [u8; 32]| -> std::future::Ready<u8> {
+            panic!("The decimals hint is used instead of looking up the mint")
+        };
+        let options = ParseOptions::new().decimals_hint(true);
+        let parse =
+            |url| smol::block_on(SolanaPayUrl::new().parse_with_options(url, lookup_fn, &options));
+
+        let url = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1.000001&spl-token=EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v&decimals=6";
+        let parsed = parse(url).unwrap();
+        assert_eq!(
+            parsed.to_url(),
+            "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1.000001&spl-token=EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v"
+        );
+
+        let url = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?decimals=2&amount=1.001&spl-token=EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+        assert_eq!(
+            parse(url),
+            Err(SolanaPayError::NumberOfDecimalsExceedsMintConfiguration)
+        );
+
+        let url = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1&spl-token=EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v&decimals=six";
+        assert_eq!(
+            parse(url),
+            Err(SolanaPayError::MalformedQuery {
+                param: String::from("decimals=six")
+            })
+        );
+
+        // The hint is not part of the spec so it is rejected by default
+        let url = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?decimals=6";
+        let outcome = smol::block_on(SolanaPayUrl::new().parse(url, lookup_fn));
+        assert_eq!(outcome, Err(SolanaPayError::InvalidQueryParam));
+    }
+
+    #[test]
+    fn invalid_base58_character() {
+        let parse =
+            |url: &str| smol::block_on(SolanaPayUrl::new().parse(url, Utils::native_sol)).err();
+
+        for c in ['0', 'O', 'I', 'l'] {
+            let recipient =
+                String::from("mvines9iiHiQTysrwkJj") + &c.to_string() + "f2gb9Ex9jXJX8ns3qwf2kN";
+            let url = String::from("solana:") + &recipient + "?amount=1";
+            assert_eq!(
+                parse(&url),
+                Some(SolanaPayError::InvalidBase58Character { c, index: 20 })
+            );
+
+            let url = String::from("solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?spl-token=")
+                + &recipient;
+            assert_eq!(
+                parse(&url),
+                Some(SolanaPayError::InvalidBase58Character { c, index: 20 })
+            );
+
+            let url = String::from("solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?reference=")
+                + &recipient;
+            assert_eq!(
+                parse(&url),
+                Some(SolanaPayError::InvalidBase58Character { c, index: 20 })
+            );
+        }
+
+        // A hex encoded public key is still reported as hex
+        let url =
+            "solana:0b4e9fd3bfc5f8a0e1e1a7b0c1a6d7d3b0e2f8a91c2d3e4f5a6b7c8d9e0f1a2b?amount=1";
+        assert_eq!(parse(url), Some(SolanaPayError::LooksLikeHexNotBase58));
+
+        // Valid characters that do not decode to a public key
+        let url = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kNN?amount=1";
+        assert_eq!(parse(url), Some(SolanaPayError::InvalidBase58Str));
+    }
+
+    #[test]
+    fn form_urlencoded_round_trip() {
+        let lookup_fn = |_| async { 6 };
+        let recipient =
+            PublicKey::from_base58("mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN").unwrap();
+
+        let url = SolanaPayUrl::new()
             .add_recipient("mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN")
             .unwrap()
-            .add_amount("0.01")
+            .add_amount("1.5")
             .unwrap()
             .add_spl_token("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v")
+            .unwrap()
+            .add_reference("7owWEdgJRWpKsiDFNU4qT2kgMe2kitPXem5Yy8VdNatx")
+            .unwrap()
+            .add_label("Michael + Co")
+            .unwrap()
+            .add_message("Thanks for all the fish")
+            .unwrap()
+            .add_spl_memo("100%")
             .unwrap();
 
-        assert_eq!(decoded_zero_zero_one_usdc, zero_zero_one_usdc_other);
+        let body = url.to_form_urlencoded();
+        assert_eq!(
+            body,
+            "amount=1.5&spl-token=EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v&reference=7owWEdgJRWpKsiDFNU4qT2kgMe2kitPXem5Yy8VdNatx&label=Michael+%2B+Co&message=Thanks+for+all+the+fish&memo=100%25"
+        );
 
-        let encode_again = decoded_zero_zero_one_usdc.to_url();
-        assert_eq!(&encode_again, zero_zero_one_usdc);
+        let parsed = smol::block_on(SolanaPayUrl::from_form_urlencoded(
+            recipient, &body, lookup_fn,
+        ))
+        .unwrap();
+        assert_eq!(parsed, url);
+        assert_eq!(parsed.to_form_urlencoded(), body);
+
+        let label_only = SolanaPayUrl::new()
+            .add_recipient("mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN")
+            .unwrap()
+            .add_label("Michael")
+            .unwrap();
+        assert_eq!(label_only.to_form_urlencoded(), "label=Michael");
+        assert_eq!(
+            smol::block_on(SolanaPayUrl::from_form_urlencoded(
+                recipient,
+                "label=Michael",
+                lookup_fn
+            )),
+            Ok(label_only)
+        );
+
+        let recipient_only = SolanaPayUrl::new()
+            .add_recipient("mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN")
+            .unwrap();
+        assert_eq!(recipient_only.to_form_urlencoded(), "");
+        assert_eq!(
+            smol::block_on(SolanaPayUrl::from_form_urlencoded(recipient, "", lookup_fn)),
+            Ok(recipient_only)
+        );
+
+        // A literal `+` in an extra parameter and the recorded order of the parameters
+        let options = ParseOptions::new()
+            .lenient_unknown_params(true)
+            .preserve_param_order(true);
+        let url = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?label=Michael%20%2B%20Co&coupon=a+b%20c&amount=1.5";
+        let parsed =
+            smol::block_on(SolanaPayUrl::new().parse_with_options(url, lookup_fn, &options))
+                .unwrap();
+
+        let body = parsed.to_form_urlencoded();
+        assert_eq!(body, "label=Michael+%2B+Co&coupon=a%2Bb%2520c&amount=1.5");
+
+        let from_body = smol::block_on(SolanaPayUrl::from_form_urlencoded_with_options(
+            recipient, &body, lookup_fn, &options,
+        ))
+        .unwrap();
+        assert_eq!(from_body, parsed);
+        assert_eq!(from_body.param_order, parsed.param_order);
+        assert_eq!(from_body.to_url(), url);
+        assert_eq!(from_body.to_form_urlencoded(), body);
     }
 
     #[test]
-    fn prompt_amount() {
-        let prompt_amount = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN&label=Michael";
+    fn amount_owned() {
+        let amount = {
+            let buffer =
+                String::from("solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=0.01");
+            let parsed =
+                smol::block_on(SolanaPayUrl::new().parse(&buffer, Utils::native_sol)).unwrap();
 
-        let decoded_prompt_amount = smol::block_on(async {
-            SolanaPayUrl::new()
-                .parse(&prompt_amount, Utils::native_sol)
-                .await
+            parsed.amount_owned()
+        };
+
+        let amount = amount.unwrap();
+        assert!(matches!(amount.as_string, Cow::Owned(_)));
+        assert_eq!(amount.as_string, "0.01");
+        assert_eq!(amount.to_mantissa_scale(), Ok((1, 2)));
+
+        assert_eq!(SolanaPayUrl::new().amount_owned(), None);
+    }
+
+    #[test]
+    fn forbid_addresses() {
+        let incinerator =
+            PublicKey::from_base58("1nc1nerator11111111111111111111111111111111").unwrap();
+        let options = ParseOptions::new().forbid_addresses(&[incinerator]);
+
+        let url = "solana:1nc1nerator11111111111111111111111111111111?amount=1";
+        let parsed = smol::block_on(SolanaPayUrl::new().parse_with_options(
+            url,
+            Utils::native_sol,
+            &options,
+        ));
+        assert_eq!(parsed, Err(SolanaPayError::ForbiddenRecipient));
+
+        assert!(smol::block_on(SolanaPayUrl::new().parse(url, Utils::native_sol)).is_ok());
+
+        let url = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1";
+        let parsed = smol::block_on(SolanaPayUrl::new().parse_with_options(
+            url,
+            Utils::native_sol,
+            &options,
+        ));
+        assert!(parsed.is_ok());
+    }
+
+    #[test]
+    fn lenient_unknown_params() {
+        let url = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1&foo=bar&label=Michael&utm_source=a%20b";
+        let options = ParseOptions::new().lenient_unknown_params(true);
+
+        let parsed = smol::block_on(SolanaPayUrl::new().parse_with_options(
+            url,
+            Utils::native_sol,
+            &options,
+        ))
+        .unwrap();
+        assert_eq!(
+            parsed.extra_params,
+            vec![
+                (Cow::Borrowed("foo"), Cow::Borrowed("bar")),
+                (Cow::Borrowed("utm_source"), Cow::Borrowed("a%20b"))
+            ]
+        );
+        assert_eq!(parsed.label.as_deref(), Some("Michael"));
+
+        let reserialized = parsed.to_url();
+        assert_eq!(
+            reserialized,
+            "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1&label=Michael&foo=bar&utm_source=a%20b"
+        );
+        let reparsed = smol::block_on(SolanaPayUrl::new().parse_with_options(
+            &reserialized,
+            Utils::native_sol,
+            &options,
+        ))
+        .unwrap();
+        assert_eq!(reparsed, parsed);
+        assert!(parsed.into_owned().to_url().contains("&foo=bar"));
+
+        let outcome = smol::block_on(SolanaPayUrl::new().parse(url, Utils::native_sol));
+        assert_eq!(outcome, Err(SolanaPayError::InvalidQueryParam));
+    }
+
+    #[test]
+    fn normalize() {
+        let url = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1.500&reference=82ZJ7nbGpixjeDCmEhUcmwXYfvurzAgGdtSMuHnUgyny&reference=7owWEdgJRWpKsiDFNU4qT2kgMe2kitPXem5Yy8VdNatx&reference=82ZJ7nbGpixjeDCmEhUcmwXYfvurzAgGdtSMuHnUgyny&label=&message=Thanks%20for%20all%20the%20fish";
+        let parsed = smol::block_on(SolanaPayUrl::new().parse(url, Utils::native_sol)).unwrap();
+
+        let normalized = parsed.normalize();
+        assert_eq!(normalized.amount.as_ref().unwrap().as_string, "1.5");
+        assert_eq!(
+            normalized
+                .references
+                .iter()
+                .map(Reference::to_base58)
+                .collect::<Vec<String>>(),
+            vec![
+                "82ZJ7nbGpixjeDCmEhUcmwXYfvurzAgGdtSMuHnUgyny",
+                "7owWEdgJRWpKsiDFNU4qT2kgMe2kitPXem5Yy8VdNatx"
+            ]
+        );
+        assert_eq!(normalized.label, None);
+        assert_eq!(
+            normalized.message.as_deref(),
+            Some("Thanks for all the fish")
+        );
+        assert_eq!(normalized.normalize(), normalized);
+
+        // The same payment written differently normalizes to the same URL
+        let same_payment = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1.5&reference=82ZJ7nbGpixjeDCmEhUcmwXYfvurzAgGdtSMuHnUgyny&reference=7owWEdgJRWpKsiDFNU4qT2kgMe2kitPXem5Yy8VdNatx&message=Thanks%20for%20all%20the%20fish";
+        let same_payment =
+            smol::block_on(SolanaPayUrl::new().parse(same_payment, Utils::native_sol)).unwrap();
+        assert_eq!(same_payment.normalize(), normalized);
+
+        assert_eq!(SolanaPayUrl::new().normalize(), SolanaPayUrl::new());
+    }
+
+    #[test]
+    fn non_ascii_query_param_key() {
+        assert_eq!(
+            QueryParam::try_from("amоunt"),
+            Err(SolanaPayError::InvalidQueryParam)
+        );
+        assert_eq!(QueryParam::try_from("foo"), Ok(QueryParam::Unsupported));
+
+        // The `о` is a Cyrillic letter that looks like the Latin `o`
+        let url = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amоunt=1";
+        let outcome = smol::block_on(SolanaPayUrl::new().parse(url, Utils::native_sol));
+        assert_eq!(outcome, Err(SolanaPayError::InvalidQueryParam));
+
+        // Not captured as an unknown parameter in lenient mode either
+        let options = ParseOptions::new().lenient_unknown_params(true);
+        let outcome = smol::block_on(SolanaPayUrl::new().parse_with_options(
+            url,
+            Utils::native_sol,
+            &options,
+        ));
+        assert_eq!(outcome, Err(SolanaPayError::InvalidQueryParam));
+    }
+
+    #[test]
+    fn case_insensitive_params() {
+        let url = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?Amount=1&SPL-TOKEN=EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+        let lookup_fn = |_| async { 6 };
+
+        let outcome = smol::block_on(SolanaPayUrl::new().parse(url, lookup_fn));
+        assert_eq!(outcome, Err(SolanaPayError::InvalidQueryParam));
+
+        let options = ParseOptions::new().case_insensitive_params(true);
+        let parsed =
+            smol::block_on(SolanaPayUrl::new().parse_with_options(url, lookup_fn, &options))
+                .unwrap();
+        assert_eq!(parsed.amount.unwrap().as_string, "1");
+        assert_eq!(
+            parsed.spl_token,
+            Some(PublicKey::from_base58("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v").unwrap())
+        );
+
+        // Duplicates are still detected regardless of the case of the keys
+        let url = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1&Amount=1";
+        let outcome =
+            smol::block_on(SolanaPayUrl::new().parse_with_options(url, lookup_fn, &options));
+        assert_eq!(outcome, Err(SolanaPayError::AmountAlreadyExists));
+    }
+
+    #[test]
+    fn require_integer_amount() {
+        let options = ParseOptions::new().require_integer_amount(true);
+        let parse = |url: &str| {
+            smol::block_on(SolanaPayUrl::new().parse_with_options(url, |_| async { 0 }, &options))
+                .err()
+        };
+
+        assert_eq!(
+            parse("solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1.5&spl-token=EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v"),
+            Some(SolanaPayError::FractionalAmountNotAllowed)
+        );
+        assert_eq!(
+            parse("solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1.0&spl-token=EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v"),
+            Some(SolanaPayError::FractionalAmountNotAllowed)
+        );
+        assert_eq!(
+            parse("solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1&spl-token=EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v"),
+            None
+        );
+        assert_eq!(
+            parse("solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN"),
+            None
+        );
+    }
+
+    #[test]
+    fn canonical_ui_amount() {
+        let options = ParseOptions::new().canonical_ui_amount(true);
+        let parse = |url: &str, options: &ParseOptions| {
+            smol::block_on(SolanaPayUrl::new().parse_with_options(url, |_| async { 6 }, options))
+                .err()
+        };
+        let spl_url = |amount: &str| {
+            String::from("solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=")
+                + amount
+                + "&spl-token=EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v"
+        };
+
+        assert_eq!(parse(&spl_url("1.5"), &options), None);
+        assert_eq!(
+            parse(&spl_url("1.5000001"), &options),
+            Some(SolanaPayError::NumberOfDecimalsExceedsMintConfiguration)
+        );
+
+        // Within the decimals of the mint but not the canonical `uiAmountString`
+        assert_eq!(
+            parse(&spl_url("1.500000"), &options),
+            Some(SolanaPayError::NonCanonicalUiAmount)
+        );
+        assert_eq!(parse(&spl_url("1.500000"), &ParseOptions::new()), None);
+
+        // Only SPL Token amounts are checked
+        let native_sol = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1.50";
+        assert_eq!(parse(native_sol, &options), None);
+    }
+
+    #[test]
+    fn empty_reference() {
+        let url =
+            "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1&reference=&label=Michael";
+
+        let outcome = smol::block_on(SolanaPayUrl::new().parse(url, Utils::native_sol));
+        assert_eq!(outcome, Err(SolanaPayError::EmptyReference));
+
+        assert_eq!(
+            SolanaPayUrl::validate_syntax(url),
+            Err(SolanaPayError::EmptyReference)
+        );
+        assert_eq!(
+            SolanaPayUrl::new().add_reference(""),
+            Err(SolanaPayError::EmptyReference)
+        );
+
+        // Collected with the other invalid references when parsing leniently
+        let options = ParseOptions::new().lenient_references(true);
+        let parsed = smol::block_on(SolanaPayUrl::new().parse_with_options(
+            url,
+            Utils::native_sol,
+            &options,
+        ))
+        .unwrap();
+        assert!(parsed.references.is_empty());
+        assert_eq!(parsed.invalid_references, vec![Cow::Borrowed("")]);
+    }
+
+    #[test]
+    fn system_program_recipient() {
+        let system_program = "11111111111111111111111111111111";
+        assert_eq!(
+            PublicKey::from_base58(system_program),
+            Ok(PublicKey([0u8; 32]))
+        );
+
+        assert_eq!(
+            SolanaPayUrl::new().add_recipient(system_program),
+            Err(SolanaPayError::SystemProgramRecipientNotAllowed)
+        );
+
+        let url = String::from("solana:") + system_program + "?amount=1";
+        let outcome = smol::block_on(SolanaPayUrl::new().parse(&url, Utils::native_sol));
+        assert_eq!(
+            outcome,
+            Err(SolanaPayError::SystemProgramRecipientNotAllowed)
+        );
+    }
+
+    #[test]
+    fn preserve_param_order() {
+        let url = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?label=x&amount=1";
+        let options = ParseOptions::new().preserve_param_order(true);
+        let parse = |url, options| {
+            smol::block_on(SolanaPayUrl::new().parse_with_options(url, |_| async { 6 }, options))
                 .unwrap()
-        });
+        };
 
-        let prompt_amount_other = SolanaPayUrl::default()
-            .add_recipient("mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN")
-            .unwrap()
-            .add_label("Michael")
+        let parsed = parse(url, &options);
+        assert_eq!(
+            parsed.param_order,
+            vec![QueryParam::Label, QueryParam::Amount]
+        );
+        assert_eq!(parsed.to_url(), url);
+
+        // The canonical order is used by default
+        let canonical = ParseOptions::new();
+        assert_eq!(
+            parse(url, &canonical).to_url(),
+            "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1&label=x"
+        );
+
+        // The same query parameters in a different order are the same URL
+        let reordered = parse(
+            "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1&label=x",
+            &options,
+        );
+        assert_ne!(reordered.param_order, parsed.param_order);
+        assert_eq!(reordered, parsed);
+        assert_eq!(
+            std::collections::HashSet::from([reordered, parsed, parse(url, &canonical)]).len(),
+            1
+        );
+
+        let url = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?reference=82ZJ7nbGpixjeDCmEhUcmwXYfvurzAgGdtSMuHnUgyny&memo=OrderId12345&foo=bar&spl-token=EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v&reference=7owWEdgJRWpKsiDFNU4qT2kgMe2kitPXem5Yy8VdNatx&amount=1.50";
+        let lenient = options.clone().lenient_unknown_params(true);
+        let parsed = parse(url, &lenient);
+        assert_eq!(parsed.to_url(), url);
+
+        // A query parameter added after parsing follows the recorded ones
+        let parsed = parsed.add_label("Michael").unwrap();
+        assert_eq!(parsed.to_url(), String::from(url) + "&label=Michael");
+
+        // Only the separator of the first query parameter is a `?`,
+        // a `?` in the value of an extra parameter is kept as is
+        let url = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?label=x&amount=1";
+        let mut parsed = parse(url, &options);
+        parsed
+            .extra_params
+            .push((Cow::Borrowed("next"), Cow::Borrowed("/pay?id=1")));
+        assert_eq!(parsed.to_url(), String::from(url) + "&next=/pay?id=1");
+    }
+
+    #[test]
+    fn secret_key_recipient() {
+        let url = String::from("solana:") + &Utils::to_base58([7u8; 64]) + "?amount=1";
+        let outcome = smol::block_on(SolanaPayUrl::new().parse(&url, Utils::native_sol));
+        assert_eq!(outcome, Err(SolanaPayError::LooksLikeSecretKeyNotPublicKey));
+    }
+
+    #[test]
+    fn parse_with_handlers() {
+        let mut handlers = std::collections::HashMap::<&str, ParamHandler>::new();
+        handlers.insert(
+            "invoice-id",
+            Box::new(|value| {
+                if value.len() == 8 && value.bytes().all(|byte| byte.is_ascii_digit()) {
+                    Ok(())
+                } else {
+                    Err(SolanaPayError::InvalidQueryParam)
+                }
+            }),
+        );
+        // Never called since `amount` is defined by the spec
+        handlers.insert(
+            "amount",
+            Box::new(|_| panic!("The amount is parsed by the spec")),
+        );
+
+        let parse = |url| {
+            smol::block_on(SolanaPayUrl::new().parse_with_handlers(
+                url,
+                Utils::native_sol,
+                &handlers,
+            ))
+        };
+
+        let url = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1&invoice-id=00012345&label=Michael";
+        let parsed = parse(url).unwrap();
+        assert_eq!(parsed.amount.unwrap().as_string, "1");
+        assert_eq!(parsed.label.as_deref(), Some("Michael"));
+
+        let url = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1&invoice-id=12345";
+        assert_eq!(parse(url), Err(SolanaPayError::InvalidQueryParam));
+
+        // A query parameter without a handler is still an error
+        let url = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1&order=12345";
+        assert_eq!(parse(url), Err(SolanaPayError::InvalidQueryParam));
+    }
+
+    #[test]
+    fn to_url_is_ascii() {
+        for text in [
+            "Michael",
+            "Thanks for all the 🐟",
+            "Café au lait ☕",
+            "支付订单",
+            "مرحبا",
+            "Zero\u{200B}width",
+            "Line\nbreak\ttab",
+            "100% & ?=#",
+        ] {
+            let url = SolanaPayUrl::new()
+                .add_recipient("mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN")
+                .unwrap()
+                .add_amount("0.01")
+                .unwrap()
+                .add_label(text)
+                .unwrap()
+                .add_message(text)
+                .unwrap()
+                .add_spl_memo(text)
+                .unwrap();
+            let encoded = url.to_url();
+            assert!(encoded.is_ascii(), "{encoded}");
+
+            let decoded =
+                smol::block_on(SolanaPayUrl::new().parse(&encoded, Utils::native_sol)).unwrap();
+            assert_eq!(decoded.label.as_deref(), Some(text));
+        }
+
+        // The values of unknown parameters captured as is are encoded if they are not ASCII
+        let url =
+            "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1&fish=\u{1F41F}&foo=a%20b";
+        let options = ParseOptions::new().lenient_unknown_params(true);
+        let parsed = smol::block_on(SolanaPayUrl::new().parse_with_options(
+            url,
+            Utils::native_sol,
+            &options,
+        ))
+        .unwrap();
+        assert_eq!(
+            parsed.to_url(),
+            "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1&fish=%F0%9F%90%9F&foo=a%20b"
+        );
+    }
+
+    #[test]
+    fn parse_many() {
+        let input = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1&label=Michael\r\n\
+            \n\
+            solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1&amount=2\n\
+            bitcoin:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN\n\
+            solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1?label=Michael\n   \n\
+            \tsolana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=0.5  \n";
+
+        let outcome = smol::block_on(SolanaPayUrl::parse_many(input, Utils::native_sol));
+        assert_eq!(outcome.len(), 5);
+
+        assert_eq!(
+            outcome[0].as_ref().unwrap().label.as_deref(),
+            Some("Michael")
+        );
+        assert_eq!(outcome[1], Err(SolanaPayError::AmountAlreadyExists));
+        assert_eq!(outcome[2], Err(SolanaPayError::InvalidSolanaPayScheme));
+        assert_eq!(outcome[3], Err(SolanaPayError::TooManySolanaPayUrlParts));
+        assert_eq!(
+            outcome[4]
+                .as_ref()
+                .unwrap()
+                .amount
+                .as_ref()
+                .unwrap()
+                .as_string,
+            "0.5"
+        );
+
+        assert!(smol::block_on(SolanaPayUrl::parse_many("\n\n", Utils::native_sol)).is_empty());
+    }
+
+    #[test]
+    fn case_insensitive_scheme() {
+        for url in [
+            "Solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1",
+            "SOLANA:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1",
+            "sOlAnA:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1",
+        ] {
+            let parsed = smol::block_on(SolanaPayUrl::new().parse(url, Utils::native_sol)).unwrap();
+            assert_eq!(
+                parsed.recipient.to_base58(),
+                "mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN"
+            );
+            assert!(parsed.amount.is_some());
+
+            let parsed =
+                smol::block_on(SolanaPayUrl::new().parse_bytes(url.as_bytes(), Utils::native_sol))
+                    .unwrap();
+            assert_eq!(
+                parsed.recipient.to_base58(),
+                "mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN"
+            );
+        }
+
+        assert_eq!(
+            SolanaPayUrl::validate_syntax("SOLANA:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN"),
+            Ok(crate::UrlStats::default())
+        );
+    }
+
+    #[test]
+    fn scheme_matches_across_entry_points() {
+        let expected = smol::block_on(SolanaPayUrl::new().parse(
+            "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1&label=Michael",
+            Utils::native_sol,
+        ))
+        .unwrap();
+
+        for url in [
+            "SOLANA:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1&label=Michael",
+            "\u{FEFF}Solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1&label=Michael",
+        ] {
+            let parsed = smol::block_on(SolanaPayUrl::new().parse(url, Utils::native_sol));
+            assert_eq!(parsed.as_ref(), Ok(&expected));
+
+            let parsed_bytes =
+                smol::block_on(SolanaPayUrl::new().parse_bytes(url.as_bytes(), Utils::native_sol));
+            assert_eq!(parsed_bytes.as_ref(), Ok(&expected));
+
+            let (parsed_collected, errors) =
+                smol::block_on(SolanaPayUrl::new().parse_collect_errors(url, Utils::native_sol));
+            assert_eq!(parsed_collected.as_ref(), Some(&expected));
+            assert!(errors.is_empty());
+
+            let spans = SolanaPayUrl::parse_spans(url).unwrap();
+            assert_eq!(
+                &url[spans.recipient],
+                "mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN"
+            );
+
+            assert!(SolanaPayUrl::validate_syntax(url).is_ok());
+        }
+    }
+
+    #[test]
+    fn transfer_checked_decimals() {
+        let spl = smol::block_on(SolanaPayUrl::new().parse(
+            "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=0.01&spl-token=EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v",
+            |_| async { 6 },
+        ))
+        .unwrap();
+        assert_eq!(spl.transfer_checked_decimals(), Some(6));
+        // The cached decimals are not part of the URL
+        assert_eq!(spl.clone().add_mint_decimals(2), spl);
+
+        let sol = smol::block_on(SolanaPayUrl::new().parse(
+            "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1",
+            Utils::native_sol,
+        ))
+        .unwrap();
+        assert_eq!(sol.transfer_checked_decimals(), None);
+        // Native SOL has no mint so setting the decimals makes no difference
+        assert_eq!(sol.add_mint_decimals(9).transfer_checked_decimals(), None);
+
+        // The mint is not looked up without an amount
+        let prompt = smol::block_on(SolanaPayUrl::new().parse(
+            "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?spl-token=EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v",
+            |_| async { 6 },
+        ))
+        .unwrap();
+        assert_eq!(prompt.transfer_checked_decimals(), None);
+        assert_eq!(
+            prompt.add_mint_decimals(6).transfer_checked_decimals(),
+            Some(6)
+        );
+    }
+
+    #[test]
+    fn equals_sign_in_value() {
+        let url = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1&label=a%3Db";
+        let parsed = smol::block_on(SolanaPayUrl::new().parse(url, Utils::native_sol)).unwrap();
+        assert_eq!(parsed.label.as_deref(), Some("a=b"));
+        assert_eq!(parsed.to_url(), url);
+
+        // A raw `=` is part of the value and is percent-encoded when encoding again
+        let url =
+            "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1&message=x=1&memo=a==";
+        let parsed = smol::block_on(SolanaPayUrl::new().parse(url, Utils::native_sol)).unwrap();
+        assert_eq!(parsed.message.as_deref(), Some("x=1"));
+        assert_eq!(parsed.spl_memo.as_deref(), Some("a=="));
+        let encoded = parsed.to_url();
+        assert_eq!(
+            encoded,
+            "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1&message=x%3D1&memo=a%3D%3D"
+        );
+        assert_eq!(
+            smol::block_on(SolanaPayUrl::new().parse(&encoded, Utils::native_sol)),
+            Ok(parsed)
+        );
+
+        let parsed =
+            smol::block_on(SolanaPayUrl::new().parse_bytes(url.as_bytes(), Utils::native_sol))
+                .unwrap();
+        assert_eq!(parsed.message.as_deref(), Some("x=1"));
+        assert!(SolanaPayUrl::validate_syntax(url).is_ok());
+    }
+
+    #[test]
+    fn parse_futures_are_send() {
+        fn assert_send<T: Send>(_: &T) {}
+
+        let url = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1";
+        let options = ParseOptions::new();
+        let handlers = std::collections::HashMap::<&str, ParamHandler>::new();
+
+        assert_send(&SolanaPayUrl::new().parse(url, Utils::native_sol));
+        assert_send(&SolanaPayUrl::new().parse_with_options(url, Utils::native_sol, &options));
+        assert_send(&SolanaPayUrl::new().parse_with_handlers(url, Utils::native_sol, &handlers));
+        assert_send(&SolanaPayUrl::new().parse_bytes(url.as_bytes(), Utils::native_sol));
+        assert_send(&SolanaPayUrl::new().parse_collect_errors(url, Utils::native_sol));
+        assert_send(&SolanaPayUrl::new().parse_maybe_encoded(url, Utils::native_sol));
+        assert_send(&SolanaPayUrl::new().parse_with_warnings(url, Utils::native_sol));
+        assert_send(&SolanaPayUrl::parse_many(url, Utils::native_sol));
+    }
+
+    #[test]
+    fn add_reference_multiple_bounded() {
+        let references = (0..=MAX_ACCOUNTS_PER_TX)
+            .map(|index| Utils::to_base58([index as u8; 32]))
+            .collect::<Vec<String>>();
+        let references = references.iter().map(String::as_str).collect::<Vec<&str>>();
+
+        assert_eq!(
+            SolanaPayUrl::new().add_reference_multiple(&references),
+            Err(SolanaPayError::TooManyReferences)
+        );
+
+        let at_limit = SolanaPayUrl::new()
+            .add_reference_multiple(&references[..MAX_ACCOUNTS_PER_TX])
             .unwrap();
+        assert_eq!(at_limit.references.len(), MAX_ACCOUNTS_PER_TX);
 
-        assert_eq!(decoded_prompt_amount, prompt_amount_other);
+        // Copies of one key collapse to one reference, like calling `add_reference` in a loop
+        let copies = [references[0]; MAX_ACCOUNTS_PER_TX + 1];
+        let collapsed = SolanaPayUrl::new().add_reference_multiple(&copies).unwrap();
+        assert_eq!(collapsed.references.len(), 1);
 
-        let encode_again = decoded_prompt_amount.to_url();
-        assert_eq!(&encode_again, prompt_amount);
+        // Adjacent duplicates are removed like in `add_reference`
+        let duplicated = SolanaPayUrl::new()
+            .add_reference(references[0])
+            .unwrap()
+            .add_reference_multiple(&[references[0], references[1], references[1], references[0]])
+            .unwrap();
+        assert_eq!(
+            duplicated
+                .references
+                .iter()
+                .map(Reference::to_base58)
+                .collect::<Vec<String>>(),
+            [references[0], references[1], references[0]]
+        );
+
+        // The remaining slots are counted, not only the length of the slice
+        let one_slot_left = SolanaPayUrl::new()
+            .add_reference_multiple(&references[..MAX_ACCOUNTS_PER_TX - 1])
+            .unwrap();
+        assert_eq!(one_slot_left.remaining_reference_slots(), 1);
+        assert_eq!(
+            one_slot_left.add_reference_multiple(&references[MAX_ACCOUNTS_PER_TX - 1..]),
+            Err(SolanaPayError::TooManyReferences)
+        );
     }
 
     #[test]
@@ -496,7 +4220,7 @@ mod url_parsing_checks {
 
         let decoded_all_fields = smol::block_on(async {
             SolanaPayUrl::new()
-                .parse(&all_fields, lookup_fn)
+                .parse(all_fields, lookup_fn)
                 .await
                 .unwrap()
                 .add_reference("7owWEdgJRWpKsiDFNU4qT2kgMe2kitPXem5Yy8VdNatx")
@@ -0,0 +1,27 @@
+//! Re-exports of the commonly used types of the crate.
+//!
+//! ```rust
+//! use solana_payments::prelude::*;
+//!
+//! let url: SolanaPayResult<SolanaPayUrl> = SolanaPayUrl::new()
+//!     .add_recipient("mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN")
+//!     .and_then(|url| url.add_amount("1"));
+//! let url = url.unwrap();
+//!
+//! let recipient: PublicKey = url.recipient;
+//! let amount: &Number = url.amount.as_ref().unwrap();
+//! let reference = Reference::new();
+//!
+//! assert_eq!(recipient.to_base58(), "mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN");
+//! assert_eq!(amount.as_string, "1");
+//! assert!(Reference::from_base58(&reference.to_base58()).is_ok());
+//! assert_eq!(
+//!     PublicKey::from_base58("0"),
+//!     Err(SolanaPayError::InvalidBase58Str)
+//! );
+//! ```
+
+pub use crate::{
+    AmountOrPrompt, Number, ParseOptions, PublicKey, QueryParam, Reference, SolanaPayError,
+    SolanaPayResult, SolanaPayUrl, TokenProgramKind, Utils,
+};
@@ -1,19 +1,55 @@
 use core::fmt;
 
-use crate::{SolanaPayResult, Utils};
+use crate::{SolanaPayError, SolanaPayResult, Utils};
 
-/// An Ed25519 Public key that may or may not be on the curve defined by Curve25519
+/// An Ed25519 Public key that may or may not be on the curve defined by Curve25519.
+///
+/// [PublicKey]s are ordered lexicographically by their 32 raw bytes,
+/// which is the same ordering Solana uses when sorting accounts by their public keys.
+/// This is not the ordering of their Base58 encoded strings.
 #[derive(Default, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
 pub struct PublicKey(pub [u8; 32]);
 
 impl PublicKey {
-    /// Convert a Base58 encoded [str] to a [PublicKey]
+    /// Convert a Base58 encoded [str] to a [PublicKey].
+    /// A hex encoded public key (64 hex characters) is a common mistake when pasting
+    /// a public key so it results in a [SolanaPayError::LooksLikeHexNotBase58] error
+    /// instead of the generic [SolanaPayError::InvalidBase58Str] error.
+    /// A Base58 encoded 64 byte keypair pasted instead of the public key results in a
+    /// [SolanaPayError::LooksLikeSecretKeyNotPublicKey] error
     pub fn from_base58(base58_str: &str) -> SolanaPayResult<Self> {
-        let outcome = Utils::from_base58(base58_str)?;
+        let outcome = Utils::from_base58(base58_str).map_err(|error| {
+            if Self::looks_like_hex(base58_str) {
+                SolanaPayError::LooksLikeHexNotBase58
+            } else if Utils::from_base58_into(base58_str, &mut [0u8; 64]) == Ok(64) {
+                SolanaPayError::LooksLikeSecretKeyNotPublicKey
+            } else {
+                error
+            }
+        })?;
 
         Ok(Self(outcome))
     }
 
+    /// Same as [PublicKey::from_base58] but also requires the [PublicKey] to be on curve.
+    /// Base58 has no checksum so a typo in a manually entered public key decodes to
+    /// another valid looking public key, about half of which are off curve.
+    /// Use this for recipients which must be wallet accounts, an off curve public key
+    /// results in a [SolanaPayError::ExpectedRecipientPublicKeyOnCurve] error
+    pub fn from_base58_checked(base58_str: &str) -> SolanaPayResult<Self> {
+        let public_key = Self::from_base58(base58_str)?;
+
+        if !public_key.is_on_ed25519_curve()? {
+            return Err(SolanaPayError::ExpectedRecipientPublicKeyOnCurve);
+        }
+
+        Ok(public_key)
+    }
+
+    pub(crate) fn looks_like_hex(base58_str: &str) -> bool {
+        base58_str.len() == 64 && base58_str.bytes().all(|byte| byte.is_ascii_hexdigit())
+    }
+
     /// Convert a [PublicKey] to Base58 encoded [String]
     pub fn to_base58(&self) -> String {
         Utils::to_base58(self.0)
@@ -24,6 +60,13 @@ impl PublicKey {
         Utils::is_on_curve25519(&self.0)
     }
 
+    /// Compare the raw bytes of two [PublicKey]s lexicographically.
+    /// This is the same as [Ord::cmp] but makes the intent clear at call sites,
+    /// for example when sorting account metas for a transaction.
+    pub fn cmp_bytes(&self, other: &Self) -> core::cmp::Ordering {
+        self.0.cmp(&other.0)
+    }
+
     /// Convert [PublicKey] to a 32 byte array
     pub fn to_bytes(&self) -> [u8; 32] {
         self.0
@@ -55,7 +98,7 @@ impl AsRef<[u8]> for PublicKey {
 
 #[cfg(test)]
 mod test_pubkey {
-    use crate::PublicKey;
+    use crate::{PublicKey, SolanaPayError};
 
     #[test]
     fn test_valid_base58() {
@@ -69,6 +112,37 @@ mod test_pubkey {
         assert!(PublicKey::from_base58(address).is_err());
     }
 
+    #[test]
+    fn byte_lexicographic_order() {
+        let mut low = [0u8; 32];
+        low[0] = 1;
+        let mut high = [0u8; 32];
+        high[0] = 2;
+        let mut highest = high;
+        highest[31] = 1;
+
+        let low = PublicKey(low);
+        let high = PublicKey(high);
+        let highest = PublicKey(highest);
+
+        assert_eq!(low.cmp_bytes(&high), core::cmp::Ordering::Less);
+        assert_eq!(highest.cmp_bytes(&high), core::cmp::Ordering::Greater);
+        assert_eq!(high.cmp_bytes(&high), core::cmp::Ordering::Equal);
+        assert_eq!(low.cmp_bytes(&high), low.cmp(&high));
+
+        let mut sorted = vec![highest, low, high];
+        sorted.sort();
+        assert_eq!(sorted, vec![low, high, highest]);
+
+        // Byte order is not the order of the Base58 encoded strings
+        let system_program = PublicKey([0u8; 32]);
+        let token_program =
+            PublicKey::from_base58("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA").unwrap();
+        let all_ones = PublicKey([255u8; 32]);
+        assert!(system_program < token_program && token_program < all_ones);
+        assert!(all_ones.to_base58() < token_program.to_base58());
+    }
+
     #[test]
     fn valid_point_on_curve() {
         let address = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
@@ -86,4 +160,52 @@ mod test_pubkey {
         assert!(PublicKey::is_on_ed25519_curve(&public_key).is_ok());
         assert!(!PublicKey::is_on_ed25519_curve(&public_key).unwrap());
     }
+
+    #[test]
+    fn hex_instead_of_base58() {
+        let hex = "0b4e9fd3bfc5f8a0e1e1a7b0c1a6d7d3b0e2f8a91c2d3e4f5a6b7c8d9e0f1a2b";
+        assert_eq!(
+            PublicKey::from_base58(hex),
+            Err(SolanaPayError::LooksLikeHexNotBase58)
+        );
+        assert_eq!(
+            PublicKey::from_base58(&hex.to_uppercase()),
+            Err(SolanaPayError::LooksLikeHexNotBase58)
+        );
+
+        let address = "mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN";
+        assert!(PublicKey::from_base58(address).is_ok());
+    }
+
+    #[test]
+    fn secret_key_instead_of_public_key() {
+        let keypair = crate::Utils::to_base58([7u8; 64]);
+        assert_eq!(
+            PublicKey::from_base58(&keypair),
+            Err(SolanaPayError::LooksLikeSecretKeyNotPublicKey)
+        );
+
+        // Neither 32 nor 64 bytes
+        let too_long = crate::Utils::to_base58([7u8; 48]);
+        assert_eq!(
+            PublicKey::from_base58(&too_long),
+            Err(SolanaPayError::InvalidBase58Str)
+        );
+    }
+
+    #[test]
+    fn from_base58_checked() {
+        let on_curve = "mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN";
+        assert_eq!(
+            PublicKey::from_base58_checked(on_curve),
+            PublicKey::from_base58(on_curve)
+        );
+
+        let off_curve = "HqAi1JjEEVS6QRvNe7gC4z8pYTuKbWkdZqCuuDpZxxQW";
+        assert!(PublicKey::from_base58(off_curve).is_ok());
+        assert_eq!(
+            PublicKey::from_base58_checked(off_curve),
+            Err(SolanaPayError::ExpectedRecipientPublicKeyOnCurve)
+        );
+    }
 }
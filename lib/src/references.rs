@@ -1,6 +1,20 @@
 use core::fmt;
 
-use crate::{RandomBytes, SolanaPayResult, Utils};
+use crate::{RandomBytes, SolanaPayError, SolanaPayResult, Utils};
+
+/// The Blake3 key derivation context used by [Reference::from_order_id]
+const REFERENCE_FROM_ORDER_ID_CONTEXT: &str = "solana-payments 2024 reference from order id";
+
+/// The internal storage of the references of a [SolanaPayUrl](crate::SolanaPayUrl).
+/// With the `smallvec` feature enabled up to 4 references, which covers most URLs,
+/// are stored inline without a heap allocation
+#[cfg(feature = "smallvec")]
+pub(crate) type References = smallvec::SmallVec<[Reference; 4]>;
+
+/// The internal storage of the references of a [SolanaPayUrl](crate::SolanaPayUrl).
+/// Enable the `smallvec` feature to store up to 4 references without a heap allocation
+#[cfg(not(feature = "smallvec"))]
+pub(crate) type References = Vec<Reference>;
 
 /// A Reference field as defined by the [Solana Pay Spec](https://docs.solanapay.com/spec#reference)
 #[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
@@ -15,11 +29,42 @@ impl Reference {
         Self(random.expose_owned())
     }
 
+    /// Same as [Reference::new] but returns a [SolanaPayError::EntropyUnavailable](crate::SolanaPayError::EntropyUnavailable)
+    /// error instead of panicking if the OS random number generator is unavailable,
+    /// like in locked-down or early-boot environments
+    pub fn try_new() -> SolanaPayResult<Self> {
+        let random = RandomBytes::try_new()?;
+
+        Ok(Self(random.expose_owned()))
+    }
+
+    /// Deterministically derive a reference from an `order_id` so that a merchant can
+    /// recompute the reference later without storing it. The `namespace` separates
+    /// references derived by different merchants or applications from the same order ID.
+    /// The derivation is a domain separated Blake3 hash so it is collision resistant,
+    /// however it is NOT secret since anyone who knows the namespace and order ID
+    /// can derive the same reference.
+    pub fn from_order_id(namespace: &[u8], order_id: &[u8]) -> Self {
+        let mut hasher = blake3::Hasher::new_derive_key(REFERENCE_FROM_ORDER_ID_CONTEXT);
+        // Prefix the namespace with its length so that a namespace and order ID
+        // cannot be shifted into each other to produce the same reference
+        hasher.update(&(namespace.len() as u64).to_le_bytes());
+        hasher.update(namespace);
+        hasher.update(order_id);
+
+        Self(*hasher.finalize().as_bytes())
+    }
+
     /// Generate a Blake3 hash of the reference
     pub fn to_hash(&self) -> blake3::Hash {
         blake3::hash(&self.0)
     }
 
+    /// Convert a 32 byte array to a [Reference]
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+
     /// Get the 32 byte array representation of a [Reference]
     pub fn to_bytes(&self) -> [u8; 32] {
         self.0
@@ -35,8 +80,28 @@ impl Reference {
         Utils::to_base58(self.0)
     }
 
-    /// Convert a [str] of Base58 encoded characters to a [Reference]
+    /// Check whether the [Reference] is equal to the Base58 encoded `base58_str`
+    pub fn eq_base58(&self, base58_str: &str) -> SolanaPayResult<bool> {
+        Ok(self.0 == Utils::from_base58(base58_str)?)
+    }
+
+    /// Same as [Reference::eq_base58] but the bytes are compared in constant time.
+    /// Use this when the reference is secret, for example when it is used
+    /// to authenticate a payment, to avoid leaking it through timing.
+    pub fn ct_eq_base58(&self, base58_str: &str) -> SolanaPayResult<bool> {
+        let other = Utils::from_base58(base58_str)?;
+
+        Ok(constant_time_eq::constant_time_eq_32(&self.0, &other))
+    }
+
+    /// Convert a [str] of Base58 encoded characters to a [Reference].
+    /// An empty [str], like the value of `reference=`, is a
+    /// [SolanaPayError::EmptyReference] error
     pub fn from_base58(base58_str: &str) -> SolanaPayResult<Self> {
+        if base58_str.is_empty() {
+            return Err(SolanaPayError::EmptyReference);
+        }
+
         let outcome = Utils::from_base58(base58_str)?;
 
         Ok(Self(outcome))
@@ -66,3 +131,66 @@ impl Default for Reference {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod test_references {
+    use crate::{Reference, SolanaPayError};
+
+    #[test]
+    fn eq_base58() {
+        let reference =
+            Reference::from_base58("7owWEdgJRWpKsiDFNU4qT2kgMe2kitPXem5Yy8VdNatx").unwrap();
+
+        assert_eq!(
+            reference.eq_base58("7owWEdgJRWpKsiDFNU4qT2kgMe2kitPXem5Yy8VdNatx"),
+            Ok(true)
+        );
+        assert_eq!(
+            reference.eq_base58("7owWEdgJRWpKsiDFNU4qT2kgMe2kitPXem5Yy8VdNaty"),
+            Ok(false)
+        );
+        assert_eq!(
+            reference.eq_base58("0OIl"),
+            Err(SolanaPayError::InvalidBase58Str)
+        );
+
+        assert_eq!(
+            reference.ct_eq_base58("7owWEdgJRWpKsiDFNU4qT2kgMe2kitPXem5Yy8VdNatx"),
+            Ok(true)
+        );
+        assert_eq!(
+            reference.ct_eq_base58("7owWEdgJRWpKsiDFNU4qT2kgMe2kitPXem5Yy8VdNaty"),
+            Ok(false)
+        );
+        assert_eq!(
+            reference.ct_eq_base58("0OIl"),
+            Err(SolanaPayError::InvalidBase58Str)
+        );
+    }
+
+    #[test]
+    fn from_order_id_is_deterministic() {
+        let first = Reference::from_order_id(b"my-shop", b"OrderId12345");
+        let second = Reference::from_order_id(b"my-shop", b"OrderId12345");
+        assert_eq!(first, second);
+        assert_eq!(Reference::from_base58(&first.to_base58()).unwrap(), second);
+
+        assert_ne!(first, Reference::from_order_id(b"my-shop", b"OrderId12346"));
+        assert_ne!(
+            first,
+            Reference::from_order_id(b"other-shop", b"OrderId12345")
+        );
+        assert_ne!(
+            Reference::from_order_id(b"my-shop", b"1"),
+            Reference::from_order_id(b"my-shop1", b"")
+        );
+    }
+
+    #[test]
+    fn try_new() {
+        let first = Reference::try_new().unwrap();
+        let second = Reference::try_new().unwrap();
+
+        assert_ne!(first, second);
+    }
+}
@@ -0,0 +1,97 @@
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{PublicKey, Reference, Utils};
+
+/// Serialize 32 bytes as a Base58 encoded string for human readable formats like JSON
+/// and as the raw 32 bytes for compact binary formats like postcard or bincode
+fn serialize_32_bytes<S: Serializer>(bytes: &[u8; 32], serializer: S) -> Result<S::Ok, S::Error> {
+    if serializer.is_human_readable() {
+        serializer.serialize_str(&Utils::to_base58(bytes))
+    } else {
+        bytes.serialize(serializer)
+    }
+}
+
+/// Deserialize 32 bytes serialized by [serialize_32_bytes]
+fn deserialize_32_bytes<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[u8; 32], D::Error> {
+    if deserializer.is_human_readable() {
+        let base58_str = String::deserialize(deserializer)?;
+
+        Utils::from_base58(&base58_str).map_err(D::Error::custom)
+    } else {
+        <[u8; 32]>::deserialize(deserializer)
+    }
+}
+
+impl Serialize for PublicKey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_32_bytes(&self.0, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for PublicKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Self(deserialize_32_bytes(deserializer)?))
+    }
+}
+
+impl Serialize for Reference {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_32_bytes(self.as_bytes(), serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Reference {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Self::from_bytes(deserialize_32_bytes(deserializer)?))
+    }
+}
+
+#[cfg(test)]
+mod test_serde_support {
+    use crate::{PublicKey, Reference};
+
+    const ADDRESS: &str = "mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN";
+
+    #[test]
+    fn public_key_human_readable() {
+        let public_key = PublicKey::from_base58(ADDRESS).unwrap();
+
+        let json = serde_json::to_string(&public_key).unwrap();
+        assert_eq!(json, format!("\"{ADDRESS}\""));
+        assert_eq!(
+            serde_json::from_str::<PublicKey>(&json).unwrap(),
+            public_key
+        );
+
+        assert!(serde_json::from_str::<PublicKey>("\"0OIl\"").is_err());
+    }
+
+    #[test]
+    fn public_key_compact() {
+        let public_key = PublicKey::from_base58(ADDRESS).unwrap();
+
+        let bytes = postcard::to_allocvec(&public_key).unwrap();
+        assert_eq!(bytes, public_key.to_bytes());
+        assert_eq!(
+            postcard::from_bytes::<PublicKey>(&bytes).unwrap(),
+            public_key
+        );
+    }
+
+    #[test]
+    fn reference_round_trip() {
+        let reference = Reference::new();
+
+        let json = serde_json::to_string(&reference).unwrap();
+        assert_eq!(json, format!("\"{}\"", reference.to_base58()));
+        assert_eq!(serde_json::from_str::<Reference>(&json).unwrap(), reference);
+
+        let bytes = postcard::to_allocvec(&reference).unwrap();
+        assert_eq!(bytes, reference.to_bytes());
+        assert_eq!(
+            postcard::from_bytes::<Reference>(&bytes).unwrap(),
+            reference
+        );
+    }
+}
@@ -0,0 +1,147 @@
+use core::ops::Range;
+
+use crate::{ParseOptions, QueryParam, SolanaPayResult, SolanaPayUrl};
+
+/// The byte offsets of the components of a Solana Pay URL within the input,
+/// returned by [SolanaPayUrl::parse_spans]. This is useful for tooling like
+/// a URL highlighter or linter.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct SolanaPayUrlSpans {
+    /// The span of the recipient
+    pub recipient: Range<usize>,
+    /// The spans of the query parameters in the order they appear in the URL
+    pub params: Vec<ParamSpan>,
+}
+
+/// The byte offsets of a query parameter of a Solana Pay URL
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ParamSpan {
+    /// The query parameter, unknown keys are [QueryParam::Unsupported]
+    pub param: QueryParam,
+    /// The span of the key of the query parameter
+    pub key: Range<usize>,
+    /// The span of the value of the query parameter
+    pub value: Range<usize>,
+}
+
+impl SolanaPayUrl<'_> {
+    /// Find the byte offsets of the recipient and of the key and value of each query parameter.
+    /// This only splits the URL the same way it is split by [SolanaPayUrl::parse], a query
+    /// parameter without a `=` is a [SolanaPayError::MalformedQuery](crate::SolanaPayError::MalformedQuery)
+    /// error. The values are not validated so no lookup function is required
+    pub fn parse_spans(solana_pay_url: &str) -> SolanaPayResult<SolanaPayUrlSpans> {
+        Self::parse_spans_with_options(solana_pay_url, &ParseOptions::default())
+    }
+
+    /// Same as [SolanaPayUrl::parse_spans] but the keys are matched to a [QueryParam]
+    /// like they are by [SolanaPayUrl::parse_with_options] using the provided [ParseOptions]
+    pub fn parse_spans_with_options(
+        solana_pay_url: &str,
+        options: &ParseOptions,
+    ) -> SolanaPayResult<SolanaPayUrlSpans> {
+        // Every part is a subslice of the input so its offset is
+        // the distance from the start of the input
+        let span = |part: &str| {
+            let start = part.as_ptr() as usize - solana_pay_url.as_ptr() as usize;
+
+            start..start + part.len()
+        };
+
+        let (recipient, queries) = Self::split_url(solana_pay_url)?;
+
+        let params = queries
+            .map(|query| {
+                let (key, value) = Self::split_query(query)?;
+
+                Ok(ParamSpan {
+                    param: Self::query_param(key, options)?,
+                    key: span(key),
+                    value: span(value),
+                })
+            })
+            .collect::<SolanaPayResult<Vec<ParamSpan>>>()?;
+
+        Ok(SolanaPayUrlSpans {
+            recipient: span(recipient),
+            params,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test_spans {
+    use crate::{ParseOptions, QueryParam, SolanaPayError, SolanaPayUrl};
+
+    #[test]
+    fn spans_map_to_substrings() {
+        let url = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1&label=Michael&foo=bar&memo=";
+
+        let spans = SolanaPayUrl::parse_spans(url).unwrap();
+        assert_eq!(
+            &url[spans.recipient.clone()],
+            "mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN"
+        );
+
+        let params = spans
+            .params
+            .iter()
+            .map(|span| (span.param, &url[span.key.clone()], &url[span.value.clone()]))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            params,
+            vec![
+                (QueryParam::Amount, "amount", "1"),
+                (QueryParam::Label, "label", "Michael"),
+                (QueryParam::Unsupported, "foo", "bar"),
+                (QueryParam::SplMemo, "memo", ""),
+            ]
+        );
+        assert_eq!(spans.params[3].value, url.len()..url.len());
+
+        let without_question_mark = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN&amount=1";
+        let spans = SolanaPayUrl::parse_spans(without_question_mark).unwrap();
+        assert_eq!(spans.recipient, 7..50);
+        assert_eq!(&without_question_mark[spans.params[0].value.clone()], "1");
+
+        let recipient_only = SolanaPayUrl::parse_spans("solana:abc").unwrap();
+        assert_eq!(recipient_only.recipient, 7..10);
+        assert!(recipient_only.params.is_empty());
+    }
+
+    #[test]
+    fn spans_of_invalid_urls() {
+        assert_eq!(
+            SolanaPayUrl::parse_spans("bitcoin:abc"),
+            Err(SolanaPayError::InvalidSolanaPayScheme)
+        );
+        assert_eq!(
+            SolanaPayUrl::parse_spans("solana:abc?amount=1?label=a"),
+            Err(SolanaPayError::TooManySolanaPayUrlParts)
+        );
+        // A query parameter is split like it is when parsing
+        assert_eq!(
+            SolanaPayUrl::parse_spans("solana:abc?amount=1&memo"),
+            Err(SolanaPayError::MalformedQuery {
+                param: String::from("memo")
+            })
+        );
+        assert_eq!(
+            SolanaPayUrl::parse_spans("solana:abc?l\u{e4}bel=a"),
+            Err(SolanaPayError::InvalidQueryParam)
+        );
+    }
+
+    #[test]
+    fn spans_with_options() {
+        let url = "solana:abc?AMOUNT=1&Label=a=b";
+
+        let spans = SolanaPayUrl::parse_spans(url).unwrap();
+        assert_eq!(spans.params[0].param, QueryParam::Unsupported);
+
+        let options = ParseOptions::new().case_insensitive_params(true);
+        let spans = SolanaPayUrl::parse_spans_with_options(url, &options).unwrap();
+        assert_eq!(spans.params[0].param, QueryParam::Amount);
+        assert_eq!(spans.params[1].param, QueryParam::Label);
+        assert_eq!(&url[spans.params[1].value.clone()], "a=b");
+    }
+}
@@ -0,0 +1,305 @@
+use crate::{
+    Number, ParseOptions, QueryParam, Reference, SolanaPayError, SolanaPayResult, SolanaPayUrl,
+    Utils, MAX_ACCOUNTS_PER_TX,
+};
+
+/// The components found in a Solana Pay URL by [SolanaPayUrl::validate_syntax]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UrlStats {
+    /// The number of references
+    pub references: usize,
+    /// Whether the URL has an amount
+    pub amount: bool,
+    /// Whether the URL has an spl-token
+    pub spl_token: bool,
+    /// Whether the URL has a label
+    pub label: bool,
+    /// Whether the URL has a message
+    pub message: bool,
+    /// Whether the URL has a memo
+    pub spl_memo: bool,
+}
+
+impl SolanaPayUrl<'_> {
+    /// Check that a Solana Pay URL is well formed and count its components.
+    /// The URL is validated exactly like it is by [SolanaPayUrl::parse] except for
+    /// the number of decimals of the amount since that requires looking up the mint,
+    /// so no lookup function is required.
+    /// No [SolanaPayUrl] is built and a well formed URL is validated without allocating,
+    /// the label, message and memo are only decoded to check that they are valid UTF-8.
+    /// This is useful for gateways that only need to reject malformed URLs
+    pub fn validate_syntax(solana_pay_url: &str) -> SolanaPayResult<UrlStats> {
+        Self::validate_syntax_with_options(solana_pay_url, &ParseOptions::default())
+    }
+
+    /// Same as [SolanaPayUrl::validate_syntax] but the URL is validated like it is by
+    /// [SolanaPayUrl::parse_with_options] using the provided [ParseOptions]
+    pub fn validate_syntax_with_options(
+        solana_pay_url: &str,
+        options: &ParseOptions,
+    ) -> SolanaPayResult<UrlStats> {
+        let (base58_public_key, queries) = Self::split_url(solana_pay_url)?;
+        let recipient = Self::recipient_key(base58_public_key)?;
+
+        let mut stats = UrlStats::default();
+        let mut spl_token = None;
+        let mut lamports_amount = false;
+        let mut decimals_hint = None;
+        let mut invalid_references = 0usize;
+        // The references limit is at most `MAX_ACCOUNTS_PER_TX`
+        // so they are kept on the stack for the account checks
+        let mut references = [Reference::from_bytes([0u8; 32]); MAX_ACCOUNTS_PER_TX];
+
+        for query in queries {
+            if let Some(decimals) = Self::decimals_hint(query, options) {
+                decimals_hint.replace(Self::parse_decimals_hint(query, decimals, decimals_hint)?);
+
+                continue;
+            }
+
+            let (key, value) = Self::split_query(query)?;
+            let query_param = Self::query_param(key, options)?;
+
+            if query_param == QueryParam::Unsupported && options.lenient_unknown_params {
+                continue;
+            }
+
+            let value = Self::trim_value(query_param, value);
+            Self::check_param_allowed(query_param, options)?;
+
+            match query_param {
+                QueryParam::Amount => {
+                    if stats.amount {
+                        return Err(SolanaPayError::AmountAlreadyExists);
+                    }
+
+                    // Converting lamports to a [Number] allocates its digits
+                    // but whether it has a fractional part is known from the lamports
+                    let is_fractional = match Self::lamports_amount(value, options)? {
+                        Some(lamports) => {
+                            lamports_amount = true;
+
+                            lamports % 10u64.pow(crate::NATIVE_SOL_DECIMAL_COUNT as u32) != 0
+                        }
+                        None => Number::new(value).parse()?.total_fractional_count != 0,
+                    };
+                    Self::check_integer_amount(is_fractional, options)?;
+
+                    stats.amount = true;
+                }
+                QueryParam::SplToken => {
+                    if stats.spl_token {
+                        return Err(SolanaPayError::SplTokenAlreadyExists);
+                    }
+
+                    spl_token.replace(Self::spl_token_key(value, options)?);
+                    stats.spl_token = true;
+                }
+                QueryParam::Reference => {
+                    Self::check_references_limit(
+                        stats.references.saturating_add(invalid_references),
+                        options,
+                    )?;
+
+                    match Self::reference_key(value) {
+                        Ok(reference) => {
+                            references[stats.references] = reference;
+                            stats.references += 1;
+                        }
+                        Err(_) if options.lenient_references => invalid_references += 1,
+                        Err(error) => return Err(error),
+                    }
+                }
+                QueryParam::Label => {
+                    if stats.label {
+                        return Err(SolanaPayError::LabelAlreadyExists);
+                    }
+
+                    Utils::url_decoded_len(value)?;
+                    stats.label = true;
+                }
+                QueryParam::Message => {
+                    if stats.message {
+                        return Err(SolanaPayError::MessageAlreadyExists);
+                    }
+
+                    Utils::url_decoded_len(value)?;
+                    stats.message = true;
+                }
+                QueryParam::SplMemo => {
+                    if stats.spl_memo {
+                        return Err(SolanaPayError::MemoAlreadyExists);
+                    }
+
+                    Self::check_memo_length(Utils::url_decoded_len(value)?)?;
+                    stats.spl_memo = true;
+                }
+                QueryParam::Unsupported => return Err(SolanaPayError::InvalidQueryParam),
+            }
+        }
+
+        Self::check_lamports_amount(lamports_amount, spl_token.as_ref())?;
+        Self::check_accounts(
+            &recipient,
+            spl_token.as_ref(),
+            &references[..stats.references],
+            options,
+        )?;
+
+        Ok(stats)
+    }
+}
+
+#[cfg(test)]
+mod test_stats {
+    use crate::{ParseOptions, QueryParam, SolanaPayError, SolanaPayUrl, UrlStats, Utils};
+
+    #[test]
+    fn full_url() {
+        let url = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=0.01&spl-token=EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v&reference=82ZJ7nbGpixjeDCmEhUcmwXYfvurzAgGdtSMuHnUgyny&reference=7owWEdgJRWpKsiDFNU4qT2kgMe2kitPXem5Yy8VdNatx&label=Michael&message=Thanks%20for%20all%20the%20fish&memo=OrderId12345";
+
+        assert_eq!(
+            SolanaPayUrl::validate_syntax(url),
+            Ok(UrlStats {
+                references: 2,
+                amount: true,
+                spl_token: true,
+                label: true,
+                message: true,
+                spl_memo: true,
+            })
+        );
+
+        assert_eq!(
+            SolanaPayUrl::validate_syntax("solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN"),
+            Ok(UrlStats::default())
+        );
+    }
+
+    #[test]
+    fn malformed_url() {
+        assert_eq!(
+            SolanaPayUrl::validate_syntax(
+                "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1&amount=2"
+            ),
+            Err(SolanaPayError::AmountAlreadyExists)
+        );
+        assert_eq!(
+            SolanaPayUrl::validate_syntax(
+                "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?reference=0OIl"
+            ),
+            Err(SolanaPayError::InvalidBase58Character { c: '0', index: 0 })
+        );
+        assert_eq!(
+            SolanaPayUrl::validate_syntax(
+                "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?foo=1"
+            ),
+            Err(SolanaPayError::InvalidQueryParam)
+        );
+        assert_eq!(
+            SolanaPayUrl::validate_syntax("bitcoin:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN"),
+            Err(SolanaPayError::InvalidSolanaPayScheme)
+        );
+    }
+
+    #[test]
+    fn same_errors_as_parse() {
+        let urls = [
+            "solana:11111111111111111111111111111111?amount=1",
+            "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1lamports",
+            "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?spl-token=sol",
+            "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?Amount=1",
+            "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=%201%20",
+            "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount= 1 ",
+            "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?spl-token=mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN",
+            "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?memo",
+        ];
+        let lenient = ParseOptions::new()
+            .lamports_amount_suffix(true)
+            .native_spl_token_alias(true)
+            .case_insensitive_params(true);
+
+        for url in urls {
+            let parsed = smol::block_on(SolanaPayUrl::new().parse(url, Utils::native_sol));
+            assert_eq!(
+                SolanaPayUrl::validate_syntax(url).err(),
+                parsed.err(),
+                "{url}"
+            );
+
+            let parsed = smol::block_on(SolanaPayUrl::new().parse_with_options(
+                url,
+                Utils::native_sol,
+                &lenient,
+            ));
+            assert_eq!(
+                SolanaPayUrl::validate_syntax_with_options(url, &lenient).err(),
+                parsed.err(),
+                "{url}"
+            );
+        }
+
+        let strict = ParseOptions::new()
+            .max_references(2)
+            .reject_reference_collisions(true)
+            .reject_duplicate_references(true)
+            .require_integer_amount(true)
+            .lamports_amount_suffix(true)
+            .decimals_hint(true)
+            .allowed_params(&[
+                QueryParam::Amount,
+                QueryParam::Reference,
+                QueryParam::SplMemo,
+            ]);
+        let lenient_values = ParseOptions::new()
+            .lenient_references(true)
+            .lenient_unknown_params(true)
+            .max_references(2);
+        let memo_too_long =
+            String::from("solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?memo=")
+                + &"%F0%9F%90%9F".repeat(crate::MAX_MEMO_BYTES / 4 + 1);
+        let urls = [
+            "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1.5",
+            "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1500000000lamports",
+            "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=2000000000lamports&decimals=6",
+            "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?decimals=6&decimals=6",
+            "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?label=Michael",
+            "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?memo=%FF",
+            "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?reference=mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN",
+            "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?reference=82ZJ7nbGpixjeDCmEhUcmwXYfvurzAgGdtSMuHnUgyny&reference=82ZJ7nbGpixjeDCmEhUcmwXYfvurzAgGdtSMuHnUgyny",
+            "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?reference=0OIl&reference=82ZJ7nbGpixjeDCmEhUcmwXYfvurzAgGdtSMuHnUgyny&reference=7owWEdgJRWpKsiDFNU4qT2kgMe2kitPXem5Yy8VdNatx",
+            "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?invoice-id=1&amount=1",
+            &memo_too_long,
+        ];
+
+        for url in urls {
+            for options in [&strict, &lenient_values] {
+                let parsed = smol::block_on(SolanaPayUrl::new().parse_with_options(
+                    url,
+                    Utils::native_sol,
+                    options,
+                ));
+                assert_eq!(
+                    SolanaPayUrl::validate_syntax_with_options(url, options).err(),
+                    parsed.err(),
+                    "{url}"
+                );
+            }
+        }
+
+        assert_eq!(
+            SolanaPayUrl::validate_syntax("solana:11111111111111111111111111111111"),
+            Err(SolanaPayError::SystemProgramRecipientNotAllowed)
+        );
+        assert_eq!(
+            SolanaPayUrl::validate_syntax_with_options(
+                "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?AMOUNT=1000lamports",
+                &lenient
+            ),
+            Ok(UrlStats {
+                amount: true,
+                ..UrlStats::default()
+            })
+        );
+    }
+}
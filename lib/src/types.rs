@@ -5,5 +5,33 @@ pub const NATIVE_SOL_DECIMAL_COUNT: u8 = 9;
 /// excluding the recipient and payer
 pub const MAX_ACCOUNTS_PER_TX: usize = 254;
 
+/// Maximum number of characters allowed in the string representation of an amount.
+/// This is generously above any real SOL or SPL token amount and guards
+/// against parsing maliciously long numbers
+pub const MAX_AMOUNT_STR_LEN: usize = 40;
+
+/// Maximum number of digits allowed in the integral part of an amount.
+/// This is the number of digits of [u64::MAX], the largest amount in base units of
+/// a mint with no decimals, and comfortably covers the max supply of SOL
+pub const MAX_AMOUNT_INTEGRAL_DIGITS: usize = 20;
+
+/// Maximum number of UTF-8 bytes in a decoded memo. The SPL Memo instruction limits the
+/// memo by the size of the transaction it is included in, which leaves room for
+/// about 566 bytes in a transfer transaction. The limit is in bytes and not characters
+/// so a memo of emoji, which are 4 bytes each, reaches it with few visible characters
+pub const MAX_MEMO_BYTES: usize = 566;
+
 /// The scheme of a Solana Pay URL
 pub const SOLANA_SCHEME: &str = "solana:";
+
+/// The query parameter of a universal link that contains the embedded Solana Pay URL
+pub const DEEP_LINK_PARAM: &str = "link";
+
+/// The mint of wrapped SOL, the SPL token representation of native SOL which has
+/// the same number of decimals as native SOL ([NATIVE_SOL_DECIMAL_COUNT])
+pub const WRAPPED_SOL_MINT: &str = "So11111111111111111111111111111111111111112";
+
+/// The value of the `spl-token` query parameter some tooling uses to denote wrapped SOL.
+/// This is not part of the Solana Pay Spec and is only accepted when enabled by
+/// [ParseOptions::native_spl_token_alias](crate::ParseOptions::native_spl_token_alias)
+pub const NATIVE_SPL_TOKEN_ALIAS: &str = "native";
@@ -2,20 +2,66 @@ use std::borrow::Cow;
 
 use crate::{SolanaPayError, SolanaPayResult};
 
+/// The maximum number of Base58 characters needed to encode 32 bytes
+const BASE58_32_BYTES_MAX_LEN: usize = 44;
+
 /// Helpers for repetitive tasks
 pub struct Utils;
 
 impl Utils {
-    /// Convert a Base58 encoded [str] to a 32 byte array
+    /// Convert a Base58 encoded [str] to a 32 byte array.
+    /// The [str] is decoded directly into the array without any intermediate allocation.
     pub fn from_base58(base58_str: &str) -> SolanaPayResult<[u8; 32]> {
         let mut buffer = [0u8; 32];
-        bs58::decode(base58_str)
+        let written = bs58::decode(base58_str)
             .onto(&mut buffer)
             .map_err(|_| SolanaPayError::InvalidBase58Str)?;
 
+        // A shorter string decodes onto only part of the buffer
+        if written != buffer.len() {
+            return Err(SolanaPayError::InvalidBase58Str);
+        }
+
         Ok(buffer)
     }
 
+    /// Check that every character of the [str] is in the Base58 alphabet used by Solana,
+    /// returning the first character that is not together with its byte index.
+    /// The alphabet excludes `0`, `O`, `I` and `l` which are easily confused with each other
+    pub fn check_base58_alphabet(base58_str: &str) -> SolanaPayResult<()> {
+        match base58_str
+            .char_indices()
+            .find(|(_, c)| !c.is_ascii_alphanumeric() || matches!(c, '0' | 'O' | 'I' | 'l'))
+        {
+            Some((index, c)) => Err(SolanaPayError::InvalidBase58Character { c, index }),
+            None => Ok(()),
+        }
+    }
+
+    /// Decode a Base58 encoded [str] into the caller provided `buffer`
+    /// without allocating, returning the number of bytes written to the `buffer`
+    pub fn from_base58_into(base58_str: &str, buffer: &mut [u8]) -> SolanaPayResult<usize> {
+        bs58::decode(base58_str)
+            .onto(buffer)
+            .map_err(|_| SolanaPayError::InvalidBase58Str)
+    }
+
+    /// Encode 32 bytes as Base58 into the `writer` without allocating a [String]
+    pub fn to_base58_into(
+        bytes: &[u8; 32],
+        writer: &mut impl core::fmt::Write,
+    ) -> core::fmt::Result {
+        let mut buffer = [0u8; BASE58_32_BYTES_MAX_LEN];
+        let written = bs58::encode(bytes)
+            .onto(&mut buffer[..])
+            .map_err(|_| core::fmt::Error)?;
+
+        // Base58 characters are always ASCII
+        let encoded = core::str::from_utf8(&buffer[..written]).map_err(|_| core::fmt::Error)?;
+
+        writer.write_str(encoded)
+    }
+
     /// Convert a slice into a Base58 encoded [String]
     pub fn to_base58(bytes: impl AsRef<[u8]>) -> String {
         bs58::encode(bytes.as_ref()).into_string()
@@ -31,13 +77,103 @@ impl Utils {
         )
     }
 
-    /// Decode a UTF-8 url encoded [str]
-    pub fn url_decode(value: &str) -> SolanaPayResult<Cow<str>> {
-        percent_encoding::percent_decode_str(value)
-            .decode_utf8()
+    /// Decode a UTF-8 url encoded [str].
+    /// A [str] without a `%` is borrowed, otherwise it is decoded into a single allocation
+    /// since the decoded value is never longer than the encoded one
+    pub fn url_decode(value: &str) -> SolanaPayResult<Cow<'_, str>> {
+        if !value.contains('%') {
+            return Ok(Cow::Borrowed(value));
+        }
+
+        let mut decoded = Vec::<u8>::with_capacity(value.len());
+        decoded.extend(percent_encoding::percent_decode_str(value));
+
+        String::from_utf8(decoded)
+            .map(Cow::Owned)
             .map_err(|_| SolanaPayError::InvalidUrlEncodedString)
     }
 
+    // Same as [Utils::url_decode] without keeping the decoded value, returning its length
+    // in bytes. The decoded bytes are validated as UTF-8 in chunks on the stack,
+    // carrying over a character that is split between two chunks
+    pub(crate) fn url_decoded_len(value: &str) -> SolanaPayResult<usize> {
+        if !value.contains('%') {
+            return Ok(value.len());
+        }
+
+        let mut chunk = [0u8; 64];
+        let mut filled = 0usize;
+        let mut decoded_len = 0usize;
+
+        for byte in percent_encoding::percent_decode_str(value) {
+            chunk[filled] = byte;
+            filled += 1;
+            decoded_len += 1;
+
+            if filled == chunk.len() {
+                filled = match core::str::from_utf8(&chunk) {
+                    Ok(_) => 0,
+                    // At most 3 bytes of an incomplete character are carried over
+                    Err(error) if error.error_len().is_none() => {
+                        chunk.copy_within(error.valid_up_to().., 0);
+                        chunk.len() - error.valid_up_to()
+                    }
+                    Err(_) => return Err(SolanaPayError::InvalidUrlEncodedString),
+                };
+            }
+        }
+
+        core::str::from_utf8(&chunk[..filled])
+            .map_err(|_| SolanaPayError::InvalidUrlEncodedString)?;
+
+        Ok(decoded_len)
+    }
+
+    /// Decode a URL encoded string without failing, for display only contexts like showing
+    /// a label produced by a sloppy encoder. A `%` that is not followed by two hex digits,
+    /// like a lone `%` or `%G1`, is replaced with the Unicode replacement character `\u{FFFD}`
+    /// and so are bytes that are not valid UTF-8 after decoding.
+    /// Use [Utils::url_decode] to reject such strings instead
+    pub fn url_decode_lossy(value: &str) -> Cow<'_, str> {
+        if !value.contains('%') {
+            return Cow::Borrowed(value);
+        }
+
+        let hex_value = |byte: u8| (byte as char).to_digit(16).map(|digit| digit as u8);
+
+        let bytes = value.as_bytes();
+        let mut decoded = Vec::<u8>::with_capacity(bytes.len());
+        let mut index = 0usize;
+
+        while index < bytes.len() {
+            let byte = bytes[index];
+
+            if byte != b'%' {
+                decoded.push(byte);
+                index += 1;
+
+                continue;
+            }
+
+            let high = bytes.get(index + 1).copied().and_then(hex_value);
+            let low = bytes.get(index + 2).copied().and_then(hex_value);
+
+            if let (Some(high), Some(low)) = (high, low) {
+                decoded.push(high << 4 | low);
+                index += 3;
+            } else {
+                decoded.extend_from_slice(
+                    char::REPLACEMENT_CHARACTER
+                        .encode_utf8(&mut [0u8; 4])
+                        .as_bytes(),
+                );
+                index += 1;
+            }
+        }
+
+        Cow::Owned(String::from_utf8_lossy(&decoded).into_owned())
+    }
+
     /// Encode a string into a URL encoded UTF-8 [String]
     pub fn url_encode(value: &str) -> String {
         percent_encoding::utf8_percent_encode(value, percent_encoding::NON_ALPHANUMERIC).to_string()
@@ -73,6 +209,30 @@ impl<const N: usize> RandomBytes<N> {
         outcome
     }
 
+    /// Same as [RandomBytes::new] but returns a [SolanaPayError::EntropyUnavailable] error
+    /// instead of panicking if the OS random number generator is unavailable
+    pub fn try_new() -> SolanaPayResult<Self> {
+        Self::try_from_entropy_source(rand_core::OsRng)
+    }
+
+    pub(crate) fn try_from_entropy_source(
+        entropy_source: impl rand_core::RngCore,
+    ) -> SolanaPayResult<Self> {
+        use rand_chacha::ChaCha20Rng;
+        use rand_core::{RngCore, SeedableRng};
+
+        let mut rng = ChaCha20Rng::from_rng(entropy_source)
+            .map_err(|_| SolanaPayError::EntropyUnavailable)?;
+        let mut buffer = [0u8; N];
+        rng.fill_bytes(&mut buffer);
+
+        let outcome = Self(buffer);
+
+        buffer.fill(0);
+
+        Ok(outcome)
+    }
+
     /// Expose the bytes as a byte array
     pub fn expose(&self) -> &[u8; N] {
         &self.0
@@ -117,3 +277,129 @@ impl<const N: usize> Default for RandomBytes<N> {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod test_utils {
+    use arrayvec::ArrayString;
+
+    use crate::{RandomBytes, SolanaPayError, Utils};
+
+    const ADDRESS: &str = "mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN";
+
+    #[test]
+    fn to_base58_into() {
+        let bytes = Utils::from_base58(ADDRESS).unwrap();
+
+        let mut encoded = ArrayString::<44>::new();
+        Utils::to_base58_into(&bytes, &mut encoded).unwrap();
+        assert_eq!(encoded.as_str(), ADDRESS);
+
+        let mut encoded = ArrayString::<44>::new();
+        Utils::to_base58_into(&[255u8; 32], &mut encoded).unwrap();
+        assert_eq!(encoded.as_str(), Utils::to_base58([255u8; 32]));
+
+        let mut too_small = ArrayString::<8>::new();
+        assert!(Utils::to_base58_into(&bytes, &mut too_small).is_err());
+    }
+
+    #[test]
+    fn from_base58_into() {
+        let mut buffer = [0u8; 32];
+        let written = Utils::from_base58_into(ADDRESS, &mut buffer).unwrap();
+        assert_eq!(written, 32);
+        assert_eq!(buffer, Utils::from_base58(ADDRESS).unwrap());
+
+        let mut buffer = [0u8; 16];
+        assert_eq!(
+            Utils::from_base58_into(ADDRESS, &mut buffer),
+            Err(SolanaPayError::InvalidBase58Str)
+        );
+
+        // Decodes to less than 32 bytes
+        assert_eq!(
+            Utils::from_base58("native"),
+            Err(SolanaPayError::InvalidBase58Str)
+        );
+    }
+
+    #[test]
+    fn url_decoded_len() {
+        let values = [
+            "Thanks for all the fish",
+            "Thanks%20for%20all%20the%20fish",
+            "%F0%9F%90%9F",
+            "%F0%9F%90",
+            "%FF",
+            "%",
+        ];
+
+        for value in values {
+            assert_eq!(
+                Utils::url_decoded_len(value),
+                Utils::url_decode(value).map(|decoded| decoded.len()),
+                "{value}"
+            );
+        }
+
+        // A character split between two chunks of 64 bytes
+        for offset in 60..=66 {
+            let value = "a".repeat(offset) + "%F0%9F%90%9F" + &"b".repeat(70);
+            assert_eq!(Utils::url_decoded_len(&value), Ok(offset + 4 + 70));
+
+            let value = "a".repeat(offset) + "%F0%9F%90" + &"b".repeat(70);
+            assert_eq!(
+                Utils::url_decoded_len(&value),
+                Err(SolanaPayError::InvalidUrlEncodedString)
+            );
+        }
+    }
+
+    #[test]
+    fn url_decode_lossy() {
+        assert_eq!(Utils::url_decode_lossy("a%20b"), "a b");
+        assert_eq!(Utils::url_decode("a%20b").unwrap(), "a b");
+
+        assert_eq!(Utils::url_decode_lossy("50%"), "50\u{FFFD}");
+        assert_eq!(Utils::url_decode_lossy("%G1"), "\u{FFFD}G1");
+        assert_eq!(Utils::url_decode_lossy("%2"), "\u{FFFD}2");
+
+        // Decodes to bytes that are not valid UTF-8
+        assert_eq!(Utils::url_decode_lossy("%FFa"), "\u{FFFD}a");
+        assert_eq!(
+            Utils::url_decode("%FFa"),
+            Err(SolanaPayError::InvalidUrlEncodedString)
+        );
+
+        assert_eq!(Utils::url_decode_lossy("Michael"), "Michael");
+    }
+
+    #[test]
+    fn entropy_unavailable() {
+        struct FailingRng;
+
+        impl rand_core::RngCore for FailingRng {
+            fn next_u32(&mut self) -> u32 {
+                unreachable!()
+            }
+
+            fn next_u64(&mut self) -> u64 {
+                unreachable!()
+            }
+
+            fn fill_bytes(&mut self, _dest: &mut [u8]) {
+                unreachable!()
+            }
+
+            fn try_fill_bytes(&mut self, _dest: &mut [u8]) -> Result<(), rand_core::Error> {
+                Err(rand_core::Error::new("entropy unavailable"))
+            }
+        }
+
+        assert_eq!(
+            RandomBytes::<32>::try_from_entropy_source(FailingRng)
+                .map(|bytes| bytes.expose_owned()),
+            Err(SolanaPayError::EntropyUnavailable)
+        );
+        assert!(RandomBytes::<32>::try_new().is_ok());
+    }
+}
@@ -0,0 +1,115 @@
+use std::future::Future;
+
+use crate::{SolanaPayResult, SolanaPayUrl};
+
+/// The number of characters of a label above which [ParseWarning::LongLabel] is returned,
+/// wallets usually truncate a label longer than this
+pub const LONG_LABEL_CHARS: usize = 64;
+
+/// An input that is allowed by the [Solana Pay Spec](https://docs.solanapay.com/spec)
+/// but is discouraged, returned by [SolanaPayUrl::parse_with_warnings]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub enum ParseWarning {
+    /// The same reference is provided more than once, which is usually a bug
+    /// in the code that created the URL
+    DuplicateReference,
+    /// The memo looks like it contains an email address. The memo is recorded on chain
+    /// by validators so it should not include private or sensitive information
+    MemoLooksLikeEmail,
+    /// The label is longer than [LONG_LABEL_CHARS] characters
+    LongLabel,
+}
+
+impl<'a> SolanaPayUrl<'a> {
+    /// Same as [SolanaPayUrl::parse] but also returns the [ParseWarning]s for inputs that
+    /// are legal but discouraged, so that merchant tooling can flag risky requests
+    /// without rejecting them
+    pub async fn parse_with_warnings<
+        F: Fn([u8; 32]) -> Fut,
+        Fut: Future<Output = u8> + Send + 'static,
+    >(
+        self,
+        solana_pay_url: &'a str,
+        lookup_fn: F,
+    ) -> SolanaPayResult<(Self, Vec<ParseWarning>)> {
+        let parsed = self.parse(solana_pay_url, lookup_fn).await?;
+        let warnings = parsed.warnings();
+
+        Ok((parsed, warnings))
+    }
+
+    fn warnings(&self) -> Vec<ParseWarning> {
+        let mut warnings = Vec::<ParseWarning>::new();
+
+        if !self.references_unique() {
+            warnings.push(ParseWarning::DuplicateReference);
+        }
+
+        if self.spl_memo.as_deref().is_some_and(looks_like_email) {
+            warnings.push(ParseWarning::MemoLooksLikeEmail);
+        }
+
+        if self
+            .label
+            .as_ref()
+            .is_some_and(|label| label.chars().count() > LONG_LABEL_CHARS)
+        {
+            warnings.push(ParseWarning::LongLabel);
+        }
+
+        warnings
+    }
+}
+
+// Any word like `local@domain.tld`, this is a heuristic and not a validation of the address
+fn looks_like_email(value: &str) -> bool {
+    value.split_whitespace().any(|word| {
+        word.split_once('@').is_some_and(|(local, domain)| {
+            !local.is_empty()
+                && domain
+                    .split_once('.')
+                    .is_some_and(|(name, tld)| !name.is_empty() && !tld.is_empty())
+        })
+    })
+}
+
+#[cfg(test)]
+mod test_warnings {
+    use crate::{ParseWarning, SolanaPayUrl, Utils};
+
+    #[test]
+    fn warnings_fire() {
+        let parse = |url| {
+            smol::block_on(SolanaPayUrl::new().parse_with_warnings(url, Utils::native_sol))
+                .unwrap()
+                .1
+        };
+
+        let url = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1&label=Michael&memo=OrderId12345";
+        assert!(parse(url).is_empty());
+
+        let url = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?reference=82ZJ7nbGpixjeDCmEhUcmwXYfvurzAgGdtSMuHnUgyny&reference=82ZJ7nbGpixjeDCmEhUcmwXYfvurzAgGdtSMuHnUgyny&memo=Receipt%20to%20michael%40example.com";
+        assert_eq!(
+            parse(url),
+            vec![
+                ParseWarning::DuplicateReference,
+                ParseWarning::MemoLooksLikeEmail
+            ]
+        );
+
+        let url = String::from("solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?label=")
+            + &"a".repeat(crate::LONG_LABEL_CHARS + 1);
+        assert_eq!(parse(&url), vec![ParseWarning::LongLabel]);
+
+        // Not an email address
+        let url = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?memo=%40michael";
+        assert!(parse(url).is_empty());
+
+        // Errors are still errors
+        let outcome = smol::block_on(SolanaPayUrl::new().parse_with_warnings(
+            "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1&amount=2",
+            Utils::native_sol,
+        ));
+        assert!(outcome.is_err());
+    }
+}
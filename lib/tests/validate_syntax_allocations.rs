@@ -0,0 +1,73 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+};
+
+use solana_payments::{ParseOptions, SolanaPayUrl};
+
+// Counts the heap allocations of the current thread only,
+// so that the test harness running on other threads is not counted
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|allocations| allocations.set(allocations.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.with(|allocations| allocations.set(allocations.get() + 1));
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+fn allocations<T>(operation: impl FnOnce() -> T) -> usize {
+    let before = ALLOCATIONS.with(Cell::get);
+    let outcome = operation();
+    let after = ALLOCATIONS.with(Cell::get);
+    drop(outcome);
+
+    after - before
+}
+
+#[test]
+fn validate_syntax_does_not_allocate() {
+    let url = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=0.01&spl-token=EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v&reference=82ZJ7nbGpixjeDCmEhUcmwXYfvurzAgGdtSMuHnUgyny&reference=7owWEdgJRWpKsiDFNU4qT2kgMe2kitPXem5Yy8VdNatx&label=Michael&message=Thanks%20for%20all%20the%20fish&memo=%F0%9F%90%9F%20OrderId12345";
+    assert_eq!(
+        allocations(|| SolanaPayUrl::validate_syntax(url).unwrap()),
+        0
+    );
+
+    let options = ParseOptions::new()
+        .case_insensitive_params(true)
+        .lamports_amount_suffix(true)
+        .decimals_hint(true)
+        .reject_duplicate_references(true)
+        .reject_reference_collisions(true);
+    let url = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?AMOUNT=1500000000lamports&decimals=9&Reference=82ZJ7nbGpixjeDCmEhUcmwXYfvurzAgGdtSMuHnUgyny&reference=7owWEdgJRWpKsiDFNU4qT2kgMe2kitPXem5Yy8VdNatx";
+    assert_eq!(
+        allocations(|| SolanaPayUrl::validate_syntax_with_options(url, &options).unwrap()),
+        0
+    );
+
+    // Parsing the same URL allocates the decoded message and memo
+    let url = "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?message=Thanks%20for%20all%20the%20fish";
+    assert_ne!(
+        allocations(|| {
+            smol::block_on(SolanaPayUrl::new().parse(url, solana_payments::Utils::native_sol))
+                .unwrap()
+        }),
+        0
+    );
+}